@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+/// Element types with a fixed-size binary encoding, used by `AVL::to_bytes` and `AVL::from_bytes`.
+///
+/// Every implementation provided by this crate encodes values in little-endian byte order.
+pub trait AsBytes: Copy {
+    /// Number of bytes taken by one encoded value
+    const SIZE: usize;
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    /// Decodes a value from exactly `SIZE` bytes
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_as_bytes {
+    ($tp:ty) => {
+        impl AsBytes for $tp {
+            const SIZE: usize = std::mem::size_of::<$tp>();
+            fn write_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn read_bytes(bytes: &[u8]) -> Self {
+                let mut buf = [0; std::mem::size_of::<$tp>()];
+                buf.copy_from_slice(bytes);
+                <$tp>::from_le_bytes(buf)
+            }
+        }
+    };
+}
+
+impl_as_bytes!(isize);
+impl_as_bytes!(i128);
+impl_as_bytes!(i64);
+impl_as_bytes!(i32);
+impl_as_bytes!(i16);
+impl_as_bytes!(i8);
+
+impl_as_bytes!(usize);
+impl_as_bytes!(u128);
+impl_as_bytes!(u64);
+impl_as_bytes!(u32);
+impl_as_bytes!(u16);
+impl_as_bytes!(u8);
+
+/// The reasons `AVL::from_bytes` can reject its input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromBytesError {
+    /// The input is shorter or longer than its length header says
+    InvalidLength,
+    /// The encoded elements are not in increasing order
+    Unsorted,
+}
+
+impl Display for FromBytesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FromBytesError::InvalidLength => f.write_str("byte length does not match the encoded element count"),
+            FromBytesError::Unsorted => f.write_str("encoded elements are not in increasing order"),
+        }
+    }
+}
+
+impl std::error::Error for FromBytesError {}
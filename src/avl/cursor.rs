@@ -0,0 +1,28 @@
+use std::marker::PhantomData;
+
+/// A position located in an `AVL` tree by `AVL::cursor`, which stays valid until the tree is next modified.
+///
+/// A cursor does not borrow the tree. Instead it records the tree's root and `generation`, so that
+/// `AVL::insert_at_cursor` can reject a cursor taken from another tree or before a modification. Inserting through
+/// a cursor keeps that cursor valid.
+pub struct Cursor<T> {
+    pub(crate) rank: usize,
+    pub(crate) generation: u64,
+    pub(crate) root: usize,
+    pub(crate) marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Cursor<T> {
+    /// Returns the number of elements before the located position
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+}
+
+impl<T> Clone for Cursor<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Cursor<T> {}
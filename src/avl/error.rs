@@ -0,0 +1,13 @@
+use std::fmt::Display;
+
+/// Returned by the `try_*` accessors of `AVL` when the tree has no elements to answer with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EmptyTreeError;
+
+impl Display for EmptyTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the tree is empty")
+    }
+}
+
+impl std::error::Error for EmptyTreeError {}
@@ -0,0 +1,50 @@
+use crate::node::Node;
+
+/// A reusable traversal stack for `AVL::iter_into_buf`, which lets repeated in-order traversals
+/// run without allocating once the stack has grown to the height of the tree
+pub struct TraversalStack<'a, T> {
+    nodes: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> TraversalStack<'a, T> {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<'a, T> Default for TraversalStack<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order iterator that keeps its pending nodes in a borrowed `TraversalStack`
+pub struct BufferedIncreasing<'a, 's, T> {
+    stack: &'s mut Vec<&'a Node<T>>,
+}
+
+impl<'a, 's, T> BufferedIncreasing<'a, 's, T> {
+    pub(crate) fn new(root: Option<&'a Node<T>>, stack: &'s mut TraversalStack<'a, T>) -> Self {
+        let stack = &mut stack.nodes;
+        stack.clear();
+        let mut rv = Self { stack };
+        rv.push_left(root);
+        rv
+    }
+
+    fn push_left(&mut self, mut cur: Option<&'a Node<T>>) {
+        while let Some(node) = cur {
+            self.stack.push(node);
+            cur = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, 's, T> Iterator for BufferedIncreasing<'a, 's, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some(&node.val)
+    }
+}
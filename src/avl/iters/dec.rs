@@ -2,11 +2,11 @@ use crate::node::Node;
 
 struct FakeNode<'a, T> {
     parent: Option<Box<FakeNode<'a, T>>>,
-    node: &'a Box<Node<T>>,
+    node: &'a Node<T>,
 }
 
 impl<'a, T> FakeNode<'a, T> {
-    fn init(node: &'a Box<Node<T>>) -> Self {
+    fn init(node: &'a Node<T>) -> Self {
         let mut parent = None;
         let mut cur = FakeNode { parent, node };
 
@@ -19,7 +19,7 @@ impl<'a, T> FakeNode<'a, T> {
         }
         cur
     }
-    fn new(node: &'a Box<Node<T>>, mut parent: Option<Box<FakeNode<'a, T>>>) -> Self {
+    fn new(node: &'a Node<T>, mut parent: Option<Box<FakeNode<'a, T>>>) -> Self {
         let mut cur = FakeNode { parent, node };
         while let Some(right_node) = &cur.node.right {
             parent = Some(Box::new(cur));
@@ -37,7 +37,7 @@ pub struct Decreasing<'a, T> {
 }
 
 impl<'a, T> Decreasing<'a, T> {
-    pub(crate) fn new(node: Option<&'a Box<Node<T>>>) -> Self {
+    pub(crate) fn new(node: Option<&'a Node<T>>) -> Self {
         match node {
             None => Self { node: None },
             Some(node) => {
@@ -48,6 +48,24 @@ impl<'a, T> Decreasing<'a, T> {
     }
 }
 
+impl<'a, T> Decreasing<'a, T> {
+    /// Starts the iteration at the last element for which `after` returns `false`, descending in `O(log(n))`.
+    /// `after` must hold for a (possibly empty) suffix of the increasing order and for nothing before it.
+    pub(crate) fn seek(root: Option<&'a Node<T>>, after: impl Fn(&T) -> bool) -> Self {
+        let mut node = None;
+        let mut cur = root;
+        while let Some(n) = cur {
+            if after(&n.val) {
+                cur = n.left.as_deref();
+            } else {
+                node = Some(Box::new(FakeNode { parent: node, node: n }));
+                cur = n.right.as_deref();
+            }
+        }
+        Self { node }
+    }
+}
+
 impl<'a, T> Iterator for Decreasing<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -123,7 +141,7 @@ impl<T> Iterator for IntoDecreasing<T> {
         match cur_node {
             None => None,
             Some(mut node) => {
-                let rv = if let Some(l_node) = node.node.left.take() {
+                if let Some(l_node) = node.node.left.take() {
                     let res = Some(node.node.val);
                     let parent = node.parent.take();
                     self.node = Some(Box::new(FakeNode2::new(l_node, parent)));
@@ -132,8 +150,7 @@ impl<T> Iterator for IntoDecreasing<T> {
                     let res = Some(node.node.val);
                     self.node = node.parent.take();
                     res
-                };
-                rv
+                }
             }
         }
     }
@@ -0,0 +1,37 @@
+use std::iter::FusedIterator;
+
+use crate::node::Node;
+
+use super::Increasing;
+
+/// Yields `(rank, &value)` pairs in increasing order, knowing exactly how many remain
+pub struct EnumerateSorted<'a, T> {
+    inner: Increasing<'a, T>,
+    rank: usize,
+    len: usize,
+}
+
+impl<'a, T> EnumerateSorted<'a, T> {
+    pub(crate) fn new(root: Option<&'a Node<T>>, start: usize, len: usize) -> Self {
+        let rank = start.min(len);
+        Self { inner: Increasing::seek_rank(root, rank), rank, len }
+    }
+}
+
+impl<'a, T> Iterator for EnumerateSorted<'a, T> {
+    type Item = (usize, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        let val = self.inner.next()?;
+        self.rank += 1;
+        Some((self.rank - 1, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.len - self.rank;
+        (left, Some(left))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for EnumerateSorted<'a, T> {}
+
+impl<'a, T> FusedIterator for EnumerateSorted<'a, T> {}
@@ -0,0 +1,27 @@
+/// Wraps an iterator whose remaining length is known up front so that `size_hint` reports it exactly,
+/// which lets collections preallocate when collecting from it
+pub(crate) struct ExactLen<I> {
+    inner: I,
+    left: usize,
+}
+
+impl<I> ExactLen<I> {
+    pub(crate) fn new(inner: I, len: usize) -> Self {
+        Self { inner, left: len }
+    }
+}
+
+impl<I: Iterator> Iterator for ExactLen<I> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        let val = self.inner.next()?;
+        self.left -= 1;
+        Some(val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for ExactLen<I> {}
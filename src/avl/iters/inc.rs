@@ -2,11 +2,11 @@ use crate::node::Node;
 
 struct FakeNode<'a, T> {
     parent: Option<Box<FakeNode<'a, T>>>,
-    node: &'a Box<Node<T>>,
+    node: &'a Node<T>,
 }
 
 impl<'a, T> FakeNode<'a, T> {
-    fn init(node: &'a Box<Node<T>>) -> Self {
+    fn init(node: &'a Node<T>) -> Self {
         let mut parent = None;
         let mut cur = FakeNode { parent, node };
 
@@ -19,7 +19,7 @@ impl<'a, T> FakeNode<'a, T> {
         }
         cur
     }
-    fn new(node: &'a Box<Node<T>>, mut parent: Option<Box<FakeNode<'a, T>>>) -> Self {
+    fn new(node: &'a Node<T>, mut parent: Option<Box<FakeNode<'a, T>>>) -> Self {
         let mut cur = FakeNode { parent, node };
         while let Some(left_node) = &cur.node.left {
             parent = Some(Box::new(cur));
@@ -37,7 +37,7 @@ pub struct Increasing<'a, T> {
 }
 
 impl<'a, T> Increasing<'a, T> {
-    pub(crate) fn new(node: Option<&'a Box<Node<T>>>) -> Self {
+    pub(crate) fn new(node: Option<&'a Node<T>>) -> Self {
         match node {
             None => Self { node: None },
             Some(node) => {
@@ -48,6 +48,46 @@ impl<'a, T> Increasing<'a, T> {
     }
 }
 
+impl<'a, T> Increasing<'a, T> {
+    /// Starts the iteration at the first element for which `before` returns `false`, descending in `O(log(n))`.
+    /// `before` must hold for a (possibly empty) prefix of the increasing order and for nothing after it.
+    pub(crate) fn seek(root: Option<&'a Node<T>>, before: impl Fn(&T) -> bool) -> Self {
+        let mut node = None;
+        let mut cur = root;
+        while let Some(n) = cur {
+            if before(&n.val) {
+                cur = n.right.as_deref();
+            } else {
+                node = Some(Box::new(FakeNode { parent: node, node: n }));
+                cur = n.left.as_deref();
+            }
+        }
+        Self { node }
+    }
+}
+
+impl<'a, T> Increasing<'a, T> {
+    /// Starts the iteration at the element of in-order position `rank`, descending in `O(log(n))` with the subtree sizes
+    pub(crate) fn seek_rank(root: Option<&'a Node<T>>, mut rank: usize) -> Self {
+        let mut node = None;
+        let mut cur = root;
+        while let Some(n) = cur {
+            let left_size = n.left_size();
+            if rank <= left_size {
+                node = Some(Box::new(FakeNode { parent: node, node: n }));
+                if rank == left_size {
+                    break;
+                }
+                cur = n.left.as_deref();
+            } else {
+                rank -= left_size + 1;
+                cur = n.right.as_deref();
+            }
+        }
+        Self { node }
+    }
+}
+
 impl<'a, T> Iterator for Increasing<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -123,7 +163,7 @@ impl<T> Iterator for IntoIncreasing<T> {
         match cur_node {
             None => None,
             Some(mut node) => {
-                let rv = if let Some(r_node) = node.node.right.take() {
+                if let Some(r_node) = node.node.right.take() {
                     let res = Some(node.node.val);
                     let parent = node.parent.take();
                     self.node = Some(Box::new(FakeNode2::new(r_node, parent)));
@@ -132,8 +172,7 @@ impl<T> Iterator for IntoIncreasing<T> {
                     let res = Some(node.node.val);
                     self.node = node.parent.take();
                     res
-                };
-                rv
+                }
             }
         }
     }
@@ -30,8 +30,8 @@ impl<'a, T> Iterator for Levels<'a, T> {
         if self.h_left > 0 {
             let mut newlist = LinkedList::new();
             for &node in &self.cur {
-                newlist.push_back(node.map(|n| n.left.as_ref()).flatten());
-                newlist.push_back(node.map(|n| n.right.as_ref()).flatten());
+                newlist.push_back(node.and_then(|n| n.left.as_ref()));
+                newlist.push_back(node.and_then(|n| n.right.as_ref()));
             }
             self.h_left -= 1;
             Some(Level {
@@ -5,4 +5,10 @@ pub use dec::*;
 mod lev;
 pub use lev::*;
 mod iter;
-pub use iter::*;
\ No newline at end of file
+pub use iter::*;
+mod enumerate;
+pub use enumerate::*;
+mod buf;
+pub use buf::*;
+mod exact;
+pub(crate) use exact::*;
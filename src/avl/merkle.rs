@@ -0,0 +1,109 @@
+use std::{hash::{Hash, Hasher}, marker::PhantomData};
+
+/// The digests of a merkle tree over the elements of an `AVL` tree, built once by `AVL::merkle_tree`.
+///
+/// The merkle tree follows the perfectly balanced split of the increasing sequence: the range of ranks `lo..hi` has
+/// the element at `lo + (hi - lo) / 2` as its middle, and its digest combines the digest of the ranks before the
+/// middle, the middle element and the digest of the ranks after it. The shape therefore depends only on the number
+/// of elements, and trees holding the same elements always share a root.
+///
+/// Building it costs `O(n)`, after which `root` is `O(1)` and `diverging_ranks` only descends into subtrees whose
+/// digests differ. The digests are not kept up to date: `generation` tells which state of the source tree they
+/// describe, so a caller can rebuild only once the tree's own `generation()` has moved on.
+pub struct MerkleTree<H> {
+    /// Digest of the range whose middle is the element at each rank
+    digests: Vec<u64>,
+    /// Digest of each element on its own, to tell a changed middle element apart from changed descendants
+    elements: Vec<u64>,
+    empty: u64,
+    generation: u64,
+    marker: PhantomData<fn() -> H>,
+}
+
+impl<H: Hasher + Default> MerkleTree<H> {
+    pub(crate) fn new<'a, T: Hash + 'a>(len: usize, mut vals: impl Iterator<Item = &'a T>, generation: u64) -> Self {
+        let mut tree = Self { digests: vec![0; len], elements: vec![0; len], empty: H::default().finish(), generation, marker: PhantomData };
+        tree.build(&mut vals, 0, len);
+        tree
+    }
+
+    /// Fills in the digests of the ranks `lo..hi` from the next `hi - lo` values of `vals`, in increasing order
+    fn build<'a, T: Hash + 'a>(&mut self, vals: &mut impl Iterator<Item = &'a T>, lo: usize, hi: usize) -> u64 {
+        if lo == hi {
+            return self.empty;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let left = self.build(vals, lo, mid);
+        let val = vals.next().expect("the tree yields len() elements");
+        let right = self.build(vals, mid + 1, hi);
+        let mut hasher = H::default();
+        hasher.write_u64(left);
+        val.hash(&mut hasher);
+        hasher.write_u64(right);
+        self.digests[mid] = hasher.finish();
+        let mut hasher = H::default();
+        val.hash(&mut hasher);
+        self.elements[mid] = hasher.finish();
+        self.digests[mid]
+    }
+}
+
+impl<H> MerkleTree<H> {
+    /// Returns the digest of the whole tree
+    #[inline]
+    pub fn root(&self) -> u64 {
+        self.digest(0, self.len())
+    }
+
+    /// Returns the number of elements the digests were built over
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    /// Returns the `generation()` of the source tree at the time the digests were built
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the digest of the ranks `lo..hi`, which must be a range of the balanced split
+    fn digest(&self, lo: usize, hi: usize) -> u64 {
+        if lo == hi {
+            self.empty
+        } else {
+            self.digests[lo + (hi - lo) / 2]
+        }
+    }
+
+    /// Returns the ranks, in increasing order, at which the elements of the two trees differ, visiting only the
+    /// subtrees whose digests disagree.
+    ///
+    /// Both trees must have the same number of elements for their ranks to line up, otherwise `None` is returned.
+    /// An inserted or removed element shifts every rank after it, so this is meant for trees whose elements were
+    /// changed in place, such as the entries of two maps with the same keys.
+    pub fn diverging_ranks(&self, other: &MerkleTree<H>) -> Option<Vec<usize>> {
+        fn visit<H>(a: &MerkleTree<H>, b: &MerkleTree<H>, lo: usize, hi: usize, out: &mut Vec<usize>) {
+            if a.digest(lo, hi) == b.digest(lo, hi) {
+                return;
+            }
+            let mid = lo + (hi - lo) / 2;
+            visit(a, b, lo, mid, out);
+            if a.elements[mid] != b.elements[mid] {
+                out.push(mid);
+            }
+            visit(a, b, mid + 1, hi, out);
+        }
+        if self.len() != other.len() {
+            return None;
+        }
+        let mut out = Vec::new();
+        visit(self, other, 0, self.len(), &mut out);
+        Some(out)
+    }
+}
@@ -3,7 +3,7 @@
 //! # Examples
 //!
 //! ```
-//! use avl_tree::AVL;
+//! use b_trees::AVL;
 //!
 //! let mut tree = AVL::new();
 //!
@@ -14,7 +14,7 @@
 //! assert_eq!(tree.len(), 3);
 //! assert_eq!(tree.height(), 2);
 //!
-//! let mut iter = tree.iter();
+//! let mut iter = tree.increasing();
 //!
 //! assert_eq!(iter.next(), Some(&1));
 //! assert_eq!(iter.next(), Some(&2));
@@ -22,16 +22,87 @@
 //! assert_eq!(iter.next(), None);
 //! ```
 
-use std::{collections::LinkedList, fmt::Debug, cmp::Ordering};
+use std::{collections::LinkedList, marker::PhantomData, fmt::{Debug, Display, Write}, cmp::Ordering, hash::{Hash, Hasher}, iter::FusedIterator, ops::{ControlFlow, RangeBounds}};
 
-use crate::Nearness;
+use crate::{node::{after_end, before_start, AvlNode}, Nearness};
 
 use self::iters::{IntoIncreasing, IntoDecreasing};
 
 use super::Node;
-use iters::{Decreasing, Increasing, Levels, IntoIter, Iter};
+use iters::{BufferedIncreasing, Decreasing, EnumerateSorted, ExactLen, Increasing, Levels, IntoIter, Iter};
 
 pub(crate) mod iters;
+pub use iters::TraversalStack;
+
+mod bytes;
+pub use bytes::*;
+mod cursor;
+pub use cursor::*;
+mod error;
+pub use error::*;
+
+mod merkle;
+pub use merkle::*;
+
+/// The outcome of `AVL::insert_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertStatus<T> {
+    /// No equal element was present, so the tree grew by one
+    Inserted,
+    /// An equal element was present and was replaced; it is carried here
+    Overwritten(T),
+}
+
+/// The outcome of `AVL::remove_tracked`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoveOutcome<T> {
+    /// The removed element, if one equal to the target was present
+    pub removed: Option<T>,
+    /// Whether the height of the whole tree went down as a result
+    pub height_decreased: bool,
+}
+
+/// Whether the element returned by `AVL::nearest_kind` equals the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NearestKind {
+    /// The target itself is in the tree
+    Exact,
+    /// The target is absent and the nearest other element was returned
+    Approximate,
+}
+
+/// Summary statistics of a tree of numbers, computed by `AVL::stats`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats<T> {
+    pub min: T,
+    pub max: T,
+    pub count: usize,
+    /// The sum of the elements, accumulated as `f64`
+    pub sum: f64,
+    pub mean: f64,
+}
+
+/// The metrics of one subtree, as yielded by `AVL::subtrees`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubtreeInfo<'a, T> {
+    /// The element at the root of the subtree
+    pub root_value: &'a T,
+    /// The height of the subtree, `1` for a leaf
+    pub height: i32,
+    /// The number of elements in the subtree
+    pub size: usize,
+}
+
+/// One step of the merge walk done by `AVL::diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diff<'a, T> {
+    /// An element only the left tree holds
+    OnlyLeft(&'a T),
+    /// An element only the right tree holds
+    OnlyRight(&'a T),
+    /// Equal elements held by both trees, left one first
+    Both(&'a T, &'a T),
+}
 
 /// ## Description
 ///
@@ -58,13 +129,21 @@ pub(crate) mod iters;
 pub struct AVL<T> {
     pub(crate) root: Option<Box<Node<T>>>,
     len: usize,
+    generation: u64,
+    rotations: u64,
+}
+
+impl<T> Default for AVL<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> AVL<T> {
     /// Creates and returns a new AVL tree
     #[inline]
     pub fn new() -> Self {
-        Self { root: None, len: 0 }
+        Self { root: None, len: 0, generation: 0, rotations: 0 }
     }
 
     /// Returns the number of nodes in this AVL tree. This operation has a strict time complexity of `O(1)`
@@ -81,12 +160,130 @@ impl<T> AVL<T> {
         }
     }
 
+    /// Returns the greatest height any AVL tree holding `len()` elements can have, roughly `1.44 * log2(n + 2)`.
+    ///
+    /// It is computed exactly in `O(log(n))` from the fewest nodes a tree of each height can hold,
+    /// `N(h) = N(h - 1) + N(h - 2) + 1`.
+    pub fn max_possible_height(&self) -> usize {
+        let (mut height, mut fewest, mut prev) = (0, 0usize, 0usize);
+        loop {
+            let next = fewest.saturating_add(prev).saturating_add(1);
+            if next > self.len {
+                return height;
+            }
+            (prev, fewest) = (fewest, next);
+            height += 1;
+        }
+    }
+
+    /// Checks in `O(log(n))` that `height()` does not exceed `max_possible_height()`.
+    /// This is a cheap sanity check rather than a full verification of every node's balance.
+    #[inline]
+    pub fn is_within_bound(&self) -> bool {
+        self.height() <= self.max_possible_height()
+    }
+
+    /// Returns a counter that is bumped by every operation that modifies the tree, including those that hand out
+    /// `&mut` access to elements such as `nth_mut`, `find_by_mut` and `fold_mut`, whether or not the element is then
+    /// changed. A lookup that finds nothing to hand out leaves it unchanged.
+    ///
+    /// Read-only operations never change it, so callers caching data derived from the tree can compare
+    /// generations to cheaply detect that their cache is stale.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the number of rotations performed to rebalance this tree over its lifetime.
+    ///
+    /// Bulk rebuilds such as `retain` lay nodes out balanced directly and perform no rotations.
+    #[inline]
+    pub fn rotation_count(&self) -> u64 {
+        self.rotations
+    }
+
+    /// Records a modification of the tree. In debug builds this also checks that the stored `len`
+    /// still agrees with the tree itself.
+    #[inline]
+    pub(crate) fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        debug_assert_eq!(self.len, self.root.as_ref().map(|r| r.size).unwrap_or(0), "stored len is out of sync with the tree");
+    }
+
+    /// Looks up an element through `find` to hand it out for changing in place. Only a successful lookup counts as a
+    /// modification of the tree, so a miss leaves `generation` alone.
+    #[inline]
+    pub(crate) fn find_mut_with<'a, R>(&'a mut self, find: impl FnOnce(&'a mut Box<Node<T>>) -> Option<R>) -> Option<R> {
+        let found = find(self.root.as_mut()?)?;
+        self.generation = self.generation.wrapping_add(1);
+        Some(found)
+    }
+
+    /// Recomputes `len` by counting the nodes of the tree, stores it and returns it.
+    ///
+    /// This is a repair tool in case the stored length ever disagrees with the tree's actual contents.
+    pub fn recount(&mut self) -> usize {
+        self.len = self.root.as_ref().map(|r| r.count_nodes()).unwrap_or(0);
+        self.generation = self.generation.wrapping_add(1);
+        self.len
+    }
+
+    /// Recomputes the cached height and subtree size of every node bottom-up in one `O(n)` pass, without rebalancing.
+    ///
+    /// Like `recount`, this is a repair tool, for trees whose nodes were assembled without keeping those fields right.
+    pub fn fix_heights(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.fix_heights();
+        }
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Returns whether every node satisfies the AVL balance condition and caches its true height and subtree size,
+    /// checked in one `O(n)` pass.
+    pub fn is_balanced(&self) -> bool {
+        self.root.as_ref().map(|r| r.checked_height().is_some()).unwrap_or(true)
+    }
+
+    /// Replaces the contents of the tree with `vals`, which must already be in increasing order,
+    /// building a perfectly balanced tree in `O(n)`
+    pub(crate) fn replace_sorted(&mut self, vals: Vec<T>) {
+        self.len = vals.len();
+        self.root = Node::build_sorted(&mut vals.into_iter(), self.len);
+        self.bump_generation();
+    }
+
+    /// Rebuilds the tree into freshly allocated nodes laid out depth-first, leaving it perfectly balanced.
+    ///
+    /// Unlike the incremental rebalancing done on every mutation, this targets the allocation layout: after long
+    /// insert/remove churn the nodes are scattered across the heap, and walking them from the root is cheaper once
+    /// each node sits next to its left subtree. The old nodes are freed before the new ones are allocated. Runs in `O(n)`.
+    pub fn compact(&mut self) {
+        let mut slots: Vec<Option<T>> = IntoIncreasing::new(self.root.take()).map(Some).collect();
+        self.root = Node::build_preorder(&mut slots);
+        self.bump_generation();
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.len = 0;
         self.root = None;
+        self.bump_generation();
     }
 
+    /// Moves every element out into a new tree and leaves this one empty, like `std::mem::take` but keeping this tree's
+    /// `generation` and `rotation_count` counting on. The returned tree starts its own counters from zero.
+    #[inline]
+    pub fn take_all(&mut self) -> AVL<T> {
+        let taken = AVL { root: self.root.take(), len: self.len, generation: 0, rotations: 0 };
+        self.len = 0;
+        self.bump_generation();
+        taken
+    }
+
+    /// Returns an iterator over the levels of the tree, from the root downwards.
+    ///
+    /// Exactly `height()` levels are produced, so an empty tree yields none. Level `i` holds `2^i` slots,
+    /// with `None` standing in for absent nodes.
     #[inline]
     pub fn levels(&self) -> impl Iterator<Item = impl Iterator<Item = Option<&T>>> {
         Levels {
@@ -107,7 +304,101 @@ impl<T> AVL<T> {
     /// More generally speaking, this implementation performs better than other implementations and also uses no extra space.
     #[inline]
     pub fn increasing(&self) -> impl Iterator<Item = &T> {
-        Increasing::new(self.root.as_ref())
+        Increasing::new(self.root.as_deref())
+    }
+
+    /// Like `increasing`, but keeps the traversal state in `stack` instead of allocating it.
+    ///
+    /// The stack is cleared on entry, so one `TraversalStack` can be reused across any number of traversals;
+    /// once it has grown to the height of the tree, no traversal allocates.
+    #[inline]
+    pub fn iter_into_buf<'a, 's>(&'a self, stack: &'s mut TraversalStack<'a, T>) -> impl Iterator<Item = &'a T> + 's {
+        BufferedIncreasing::new(self.root.as_deref(), stack)
+    }
+
+    /// Lists the nodes whose subtrees differ in height, in increasing order, with their balance factor: the height of
+    /// the left subtree minus that of the right one.
+    ///
+    /// In a balanced tree every listed factor is `-1` or `1`. Nodes with such a factor are the ones whose next insert
+    /// on the taller side triggers a rotation.
+    pub fn imbalance_report(&self) -> Vec<(&T, i32)> {
+        fn visit<'a, T>(node: &'a Node<T>, report: &mut Vec<(&'a T, i32)>) {
+            if let Some(left) = &node.left {
+                visit(left, report);
+            }
+            if node.bf() != 0 {
+                report.push((&node.val, node.bf()));
+            }
+            if let Some(right) = &node.right {
+                visit(right, report);
+            }
+        }
+        let mut report = Vec::new();
+        if let Some(root) = &self.root {
+            visit(root, &mut report);
+        }
+        report
+    }
+
+    /// Yields the height and size of the subtree rooted at every node, visiting the nodes in pre-order so that each
+    /// subtree comes before the subtrees nested in it, starting with the whole tree.
+    pub fn subtrees(&self) -> impl ExactSizeIterator<Item = SubtreeInfo<'_, T>> {
+        let mut stack: Vec<&Node<T>> = self.root.as_deref().into_iter().collect();
+        let infos = std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.right.as_deref());
+            stack.extend(node.left.as_deref());
+            Some(SubtreeInfo { root_value: &node.val, height: node.height, size: node.size })
+        });
+        ExactLen::new(infos, self.len)
+    }
+
+    /// Yields the elements whose in-order positions lie in `start..end`, like slicing a sorted `Vec`.
+    ///
+    /// `end` is clamped to `len()`, and the range is empty if `start >= end`. Rank `start` is reached in `O(log(n))`.
+    pub fn range_by_index(&self, start: usize, end: usize) -> impl ExactSizeIterator<Item = &T> {
+        self.iter_from_rank(start).take(end.min(self.len).saturating_sub(start))
+    }
+
+    /// Yields every `step`-th element in increasing order, starting with the minimum.
+    ///
+    /// Each element is reached directly by rank through the subtree sizes in `O(log(n))`, so the nodes in between are
+    /// never visited. Panics if `step` is zero.
+    pub fn stride(&self, step: usize) -> impl ExactSizeIterator<Item = &T> {
+        assert!(step != 0, "stride step must be non-zero");
+        let count = self.len.div_ceil(step);
+        let mut rank = 0;
+        let vals = std::iter::from_fn(move || {
+            let val = self.nth_from_start(rank)?;
+            rank = rank.saturating_add(step);
+            Some(val)
+        });
+        ExactLen::new(vals, count)
+    }
+
+    /// Returns `(rank, &value)` pairs in increasing order, where `rank` is the in-order position of the value.
+    #[inline]
+    pub fn enumerate_sorted(&self) -> impl ExactSizeIterator<Item = (usize, &T)> + FusedIterator {
+        EnumerateSorted::new(self.root.as_deref(), 0, self.len)
+    }
+
+    /// Returns an in-order iterator that starts at the element of rank `start`, reached in `O(log(n))`.
+    /// The iterator is empty if `start >= len()`.
+    #[inline]
+    pub fn iter_from_rank(&self, start: usize) -> impl ExactSizeIterator<Item = &T> + FusedIterator {
+        EnumerateSorted::new(self.root.as_deref(), start, self.len).map(|(_, v)| v)
+    }
+
+    /// Folds the elements in increasing order, yielding a clone of the accumulated state after each one.
+    ///
+    /// Unlike `Iterator::scan`, the returned iterator knows its exact length, one state per element.
+    #[inline]
+    pub fn scan<'a, B: Clone + 'a, F: FnMut(&mut B, &T) + 'a>(&'a self, init: B, mut f: F) -> impl ExactSizeIterator<Item = B> + 'a {
+        let mut acc = init;
+        self.iter_from_rank(0).map(move |v| {
+            f(&mut acc, v);
+            acc.clone()
+        })
     }
 
     #[inline]
@@ -115,6 +406,54 @@ impl<T> AVL<T> {
         IntoIncreasing::new(self.root)
     }
 
+    /// Splits the tree into `n` balanced trees holding contiguous runs of the increasing order, whose lengths differ
+    /// by at most one. Concatenating the chunks in order gives back the original sequence.
+    ///
+    /// The chunks are bulk-loaded straight from the increasing order in `O(n)` total. When `n > len()` the trailing
+    /// chunks are empty. Panics if `n` is zero.
+    pub fn split_into(self, n: usize) -> Vec<AVL<T>> {
+        assert!(n != 0, "cannot split into zero chunks");
+        let (base, extra) = (self.len / n, self.len % n);
+        let mut vals = IntoIncreasing::new(self.root);
+        (0..n)
+            .map(|i| {
+                let mut chunk = AVL::new();
+                chunk.len = base + usize::from(i < extra);
+                chunk.root = Node::build_sorted(&mut vals, chunk.len);
+                chunk
+            })
+            .collect()
+    }
+
+    /// Consumes the tree, converting every element with `f` and rebuilding a balanced `AVL<U>` out of the results.
+    ///
+    /// The results are sorted again since `f` need not preserve the order. The first error is returned as soon as `f`
+    /// produces it, before any tree is built.
+    pub fn try_map<U: Ord, E, F: FnMut(T) -> Result<U, E>>(self, f: F) -> Result<AVL<U>, E> {
+        let vals = IntoIncreasing::new(self.root).map(f).collect::<Result<Vec<U>, E>>()?;
+        Ok(AVL::from_unsorted_vec(vals))
+    }
+
+    /// Collects the elements into `C` in increasing order, through an iterator that reports its exact length
+    /// so that `C` can preallocate.
+    #[inline]
+    pub fn collect_into<C: FromIterator<T>>(self) -> C {
+        let len = self.len;
+        ExactLen::new(IntoIncreasing::new(self.root), len).collect()
+    }
+
+    /// Like `collect_into`, but collects references to the elements, leaving the tree in place.
+    #[inline]
+    pub fn collect_refs<'a, C: FromIterator<&'a T>>(&'a self) -> C {
+        self.iter_from_rank(0).collect()
+    }
+
+    /// Moves the elements into a `Vec` in increasing order, allocating it exactly once.
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.collect_into()
+    }
+
     #[inline]
     pub fn into_decreasing(self) -> impl Iterator<Item = T> {
         IntoDecreasing::new(self.root)
@@ -122,7 +461,7 @@ impl<T> AVL<T> {
 
     #[inline]
     pub fn decreasing(&self) -> impl Iterator<Item = &T> {
-        Decreasing::new(self.root.as_ref())
+        Decreasing::new(self.root.as_deref())
     }
 
     #[inline]
@@ -130,79 +469,435 @@ impl<T> AVL<T> {
         self.len == 0
     }
 
+    /// Returns the first element in increasing order, found by walking the left spine in `O(log(n))`.
+    #[inline]
+    pub fn first(&self) -> Option<&T> {
+        self.root.as_ref().map(|r| r.find_min())
+    }
+
+    /// Returns the last element in increasing order, found by walking the right spine in `O(log(n))`.
+    #[inline]
+    pub fn last(&self) -> Option<&T> {
+        self.root.as_ref().map(|r| r.find_max())
+    }
+
+    /// Returns the element at position `index` in increasing order, using the subtree sizes to descend in `O(log(n))`.
+    #[inline]
+    pub fn nth_from_start(&self, index: usize) -> Option<&T> {
+        self.root.as_ref()?.nth(index)
+    }
+
+    /// Returns a mutable reference to the element at position `index` in increasing order, or `None` if out of range.
+    ///
+    /// The element must not be changed in a way that moves it relative to its neighbours, otherwise the tree
+    /// is left out of order and lookups may miss elements.
+    #[inline]
+    pub fn nth_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.find_mut_with(|root| root.nth_mut(index))
+    }
+
+    /// Returns the two elements surrounding the fractional rank `q * (len() - 1)`, together with how far that rank lies
+    /// past the first of them, for linearly interpolating quantiles.
+    ///
+    /// When the rank lands exactly on an element both references are that element and the weight is `0.0`. Returns
+    /// `None` on an empty tree or when `q` is not within `0.0..=1.0`. Both elements are reached by rank in `O(log(n))`.
+    pub fn interpolate_at(&self, q: f64) -> Option<(&T, &T, f64)> {
+        if self.len == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let pos = q * (self.len - 1) as f64;
+        let lower = (pos.floor() as usize).min(self.len - 1);
+        let upper = (pos.ceil() as usize).min(self.len - 1);
+        Some((self.nth_from_start(lower)?, self.nth_from_start(upper)?, pos - lower as f64))
+    }
+
+    /// Returns the element at position `index` counting back from the largest element, in `O(log(n))`.
+    #[inline]
+    pub fn nth_from_end(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        self.nth_from_start(self.len - 1 - index)
+    }
+
+    /// Returns whether both trees have exactly the same shape with equal values in corresponding nodes.
+    ///
+    /// Unlike `==`, which only compares the elements in increasing order, this tells apart trees holding
+    /// the same elements that were arranged differently by their insertion history.
+    pub fn structurally_eq(&self, other: &AVL<T>) -> bool
+    where
+        T: PartialEq,
+    {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => a.structurally_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Calls `f` on every element in increasing order, stopping at the first `Err` and returning it.
+    pub fn try_for_each<E, F: FnMut(&T) -> Result<(), E>>(&self, mut f: F) -> Result<(), E> {
+        let flow = match &self.root {
+            Some(root) => root.try_for_each(&mut |v| match f(v) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => ControlFlow::Break(e),
+            }),
+            None => ControlFlow::Continue(()),
+        };
+        match flow {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(e) => Err(e),
+        }
+    }
+
+    /// Looks up an element with a comparator instead of a full value of type `T`.
+    ///
+    /// `f` receives stored elements and returns how the sought element compares to them, such as
+    /// `|r| target_id.cmp(&r.id)` for a tree ordered by an `id` field.
+    #[inline]
+    pub fn find_by(&self, f: impl FnMut(&T) -> Ordering) -> Option<&T> {
+        self.root.as_ref()?.get_by(f)
+    }
+
+    /// Mutable counterpart of `find_by`. The element must not be changed in a way that alters its ordering.
+    #[inline]
+    pub fn find_by_mut(&mut self, f: impl FnMut(&T) -> Ordering) -> Option<&mut T> {
+        self.find_mut_with(|root| root.get_mut_by(f))
+    }
+
+    /// Folds over the elements in increasing order while mutating them, recursing directly over the nodes.
+    ///
+    /// `f` must not change the relative order of the elements, since the tree is not rearranged afterwards.
+    pub fn fold_mut<B, F: FnMut(B, &mut T) -> B>(&mut self, init: B, mut f: F) -> B {
+        match &mut self.root {
+            Some(root) => {
+                let acc = root.fold_mut(init, &mut f);
+                self.bump_generation();
+                acc
+            }
+            None => init,
+        }
+    }
+
+    /// Returns the first element in increasing order for which `pred` holds, in a single `O(log(n))` descent.
+    ///
+    /// `pred` must be monotone: false for a (possibly empty) prefix of the increasing order and true for everything after
+    /// it, like `|t| t >= cutoff`. Use `find` for arbitrary predicates.
+    pub fn first_where<P: FnMut(&T) -> bool>(&self, mut pred: P) -> Option<&T> {
+        let mut found = None;
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            if pred(&node.val) {
+                found = Some(&node.val);
+                cur = node.left.as_deref();
+            } else {
+                cur = node.right.as_deref();
+            }
+        }
+        found
+    }
+
+    /// Returns the first element in increasing order that satisfies `pred`, without visiting the rest of the tree.
+    pub fn find<P: FnMut(&T) -> bool>(&self, mut pred: P) -> Option<&T> {
+        self.find_map(|v| if pred(v) { Some(v) } else { None })
+    }
+
+    /// Returns the first non-`None` result of `f` applied to the elements in increasing order.
+    pub fn find_map<'a, B, F: FnMut(&'a T) -> Option<B>>(&'a self, mut f: F) -> Option<B> {
+        let root = self.root.as_ref()?;
+        match root.try_for_each(&mut |v| match f(v) {
+            Some(b) => ControlFlow::Break(b),
+            None => ControlFlow::Continue(()),
+        }) {
+            ControlFlow::Break(b) => Some(b),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
 }
 
 impl<T: Ord> AVL<T> {
+    /// Builds a balanced tree from unsorted elements by sorting `v` in place and bulk-loading it in `O(n)`.
+    ///
+    /// This avoids the per-element rotations and descents of collecting with `FromIterator`. Duplicates are kept,
+    /// with equal elements in their original relative order, just as repeated `insert` calls would leave them.
+    pub fn from_unsorted_vec(mut v: Vec<T>) -> Self {
+        v.sort();
+        let mut avl = Self::new();
+        avl.replace_sorted(v);
+        avl
+    }
+
+    /// Like `from_unsorted_vec`, but keeps only the first occurrence, in the original order, of every run of equal elements.
+    pub fn from_unsorted_vec_distinct(mut v: Vec<T>) -> Self {
+        v.sort();
+        v.dedup();
+        let mut avl = Self::new();
+        avl.replace_sorted(v);
+        avl
+    }
+
+    /// Moves the elements into a `std::collections::BTreeSet`, which keeps a single element out of every run of equal ones.
+    #[inline]
+    pub fn into_btreeset(self) -> std::collections::BTreeSet<T> {
+        self.collect_into()
+    }
+
+    /// Inserts `val` even if equal elements are already present, placing it after them in increasing order.
     #[inline]
     pub fn insert(&mut self, val: T) {
         if let Some(root) = &mut self.root {
-            root.insert(val);
+            root.insert(val, &mut self.rotations);
         } else {
             self.root = Some(Box::new(Node::new(val)))
         }
-        self.len += 1
+        self.len += 1;
+        self.bump_generation();
     }
 
     #[inline]
     pub fn insert_distinct(&mut self, val: T) -> bool {
-        if let Some(root) = &mut self.root {
-            if root.insert_distinct(val) {
-                self.len += 1;
-                true
-            } else {
-                false
-            }
+        matches!(self.insert_status(val), InsertStatus::Inserted)
+    }
+
+    /// Inserts `val`, replacing an equal element if there is one, and reports which of the two happened.
+    /// On a replacement the previously stored element is handed back.
+    #[inline]
+    pub fn insert_status(&mut self, val: T) -> InsertStatus<T> {
+        let replaced = if let Some(root) = &mut self.root {
+            root.insert_distinct(val, &mut self.rotations)
         } else {
             self.root = Some(Box::new(Node::new(val)));
+            None
+        };
+        if replaced.is_none() {
             self.len += 1;
+        }
+        self.bump_generation();
+        match replaced {
+            Some(old) => InsertStatus::Overwritten(old),
+            None => InsertStatus::Inserted,
+        }
+    }
+
+    /// Locates the position of the first element `>= val` and returns it as a cursor for `insert_at_cursor`.
+    pub fn cursor(&self, val: &T) -> Cursor<T> {
+        Cursor {
+            rank: self.root.as_ref().map(|r| r.count_less_by(|en| val.cmp(en))).unwrap_or(0),
+            generation: self.generation,
+            root: self.root_addr(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns whether `cursor` was taken from this tree and the tree has not been modified since.
+    pub fn is_cursor_valid(&self, cursor: &Cursor<T>) -> bool {
+        cursor.generation == self.generation && cursor.root == self.root_addr() && cursor.rank <= self.len
+    }
+
+    /// Inserts `val`, which is expected to belong next to the position of `cursor`, and moves the cursor to just after
+    /// the inserted element, so that a run of nearby values can be inserted one after another through one cursor.
+    ///
+    /// Panics if `cursor` is not valid for this tree, see `is_cursor_valid`. The result is always the same as that of
+    /// `insert`. While `val` does belong at the cursor, the descent follows the subtree sizes towards the cursor's rank
+    /// and compares `val` against only its two neighbours, so appending through a cursor at `len` costs no more than
+    /// `push_back`. A value that belongs elsewhere falls back to a single ordinary descent.
+    pub fn insert_at_cursor(&mut self, cursor: &mut Cursor<T>, val: T) {
+        assert!(self.is_cursor_valid(cursor), "cursor does not point into the current state of this tree");
+        let rank = match &mut self.root {
+            None => {
+                self.root = Some(Box::new(Node::new(val)));
+                0
+            }
+            Some(root) => match root.insert_at_rank(cursor.rank, val, None, None, &mut self.rotations) {
+                Ok(()) => cursor.rank,
+                Err(val) => root.insert_ranked(val, &mut self.rotations),
+            },
+        };
+        self.len += 1;
+        self.bump_generation();
+        *cursor = Cursor { rank: rank + 1, generation: self.generation, root: self.root_addr(), marker: PhantomData };
+    }
+
+    fn root_addr(&self) -> usize {
+        self.root.as_deref().map(|r| r as *const Node<T> as usize).unwrap_or(0)
+    }
+
+    /// Inserts `val` only if no equal element is present, leaving an existing one untouched, and returns whether
+    /// it was inserted. This takes a single descent.
+    #[inline]
+    pub fn insert_if_absent(&mut self, val: T) -> bool {
+        let inserted = if let Some(root) = &mut self.root {
+            root.insert_absent(val, &mut self.rotations)
+        } else {
+            self.root = Some(Box::new(Node::new(val)));
             true
+        };
+        if inserted {
+            self.len += 1;
+            self.bump_generation();
         }
+        inserted
+    }
+
+    /// Inserts `val` like `insert_status` and returns a reference to the element now stored at its position,
+    /// which is `val` itself whether it was added or overwrote an equal element.
+    ///
+    /// The insertion reports the rank it stored `val` at, so the element is then fetched through the subtree sizes
+    /// without comparing anything again.
+    pub fn insert_ref(&mut self, val: T) -> &T {
+        let rank = match &mut self.root {
+            Some(root) => {
+                let (rank, replaced) = root.insert_distinct_ranked(val, &mut self.rotations);
+                if replaced.is_none() {
+                    self.len += 1;
+                }
+                rank
+            }
+            None => {
+                self.root = Some(Box::new(Node::new(val)));
+                self.len += 1;
+                0
+            }
+        };
+        self.bump_generation();
+        self.nth_from_start(rank).expect("the element was just stored at this rank")
+    }
+
+    /// Appends `val` after the current maximum, following only the right spine of the tree.
+    ///
+    /// This skips every comparison that `insert` would make and is meant for append-only ingestion of
+    /// increasing values. `val` must be greater than or equal to `max()`; this is checked in debug builds only,
+    /// and breaking it leaves the tree out of order.
+    #[inline]
+    pub fn insert_unchecked_sorted(&mut self, val: T) {
+        debug_assert!(self.max().map(|m| m <= &val).unwrap_or(true), "insert_unchecked_sorted called with a value below max()");
+        if let Some(root) = &mut self.root {
+            root.push_back(val, &mut self.rotations);
+        } else {
+            self.root = Some(Box::new(Node::new(val)))
+        }
+        self.len += 1;
+        self.bump_generation();
+    }
+
+    /// Inserts `val` at in-order position `rank`, which the caller has found to be where `val` belongs, following the
+    /// subtree sizes without comparing `val` against anything.
+    pub(crate) fn insert_nth(&mut self, rank: usize, val: T) {
+        debug_assert!(rank <= self.len, "insert_nth given a rank past the end");
+        if let Some(root) = &mut self.root {
+            root.insert_nth(rank, val, &mut self.rotations);
+        } else {
+            self.root = Some(Box::new(Node::new(val)))
+        }
+        self.len += 1;
+        self.bump_generation();
+    }
+
+    /// Removes and returns the element at in-order position `index`, located through the subtree sizes in `O(log(n))`.
+    /// Returns `None` if `index >= len()`.
+    pub fn remove_nth(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let (removed, root) = self.root.take()?.remove_nth(index, &mut self.rotations);
+        self.root = root;
+        self.len -= 1;
+        self.bump_generation();
+        removed
+    }
+
+    /// Removes and returns the minimum element in `O(log(n))`.
+    pub fn pop_first(&mut self) -> Option<T> {
+        let (min, root) = self.root.take()?.pop_min(&mut self.rotations);
+        self.root = root;
+        self.len -= 1;
+        self.bump_generation();
+        Some(min)
+    }
+
+    /// Removes and returns the maximum element in `O(log(n))`.
+    pub fn pop_last(&mut self) -> Option<T> {
+        let (max, root) = self.root.take()?.pop_max(&mut self.rotations);
+        self.root = root;
+        self.len -= 1;
+        self.bump_generation();
+        Some(max)
     }
 
     #[inline]
     pub fn remove_by(&mut self, f: impl FnMut(&T) -> Ordering) -> Option<T> {
         let mut res = None;
         self.root = if let Some(root) = self.root.take() {
-            let (v, val) = root.remove_by(f);
+            let (v, val) = root.remove_by(f, &mut self.rotations);
             res = v;
             val
         } else {
             None
         };
         if res.is_some() {
-            self.len -= 1
+            self.len -= 1;
+            self.bump_generation();
         }
         res
     }
 
+    /// Removes the stored element equal to `val` and returns it.
+    ///
+    /// The returned element is the one that was in the tree, not `val`, so any fields the ordering ignores come
+    /// back as they were stored.
+    #[inline]
+    pub fn take(&mut self, val: &T) -> Option<T> {
+        self.remove(val)
+    }
+
+    /// Like `take`, but locates the element with `f`, which reports how the target compares to each visited element.
+    #[inline]
+    pub fn take_by(&mut self, f: impl FnMut(&T) -> Ordering) -> Option<T> {
+        self.remove_by(f)
+    }
+
     #[inline]
     pub fn remove(&mut self, val: &T) -> Option<T> {
         let mut res = None;
         self.root = if let Some(root) = self.root.take() {
-            let (v, val) = root.delete(&val);
+            let (v, val) = root.delete(val, &mut self.rotations);
             res = v;
             val
         } else {
             None
         };
         if res.is_some() {
-            self.len -= 1
+            self.len -= 1;
+            self.bump_generation();
         }
         res
     }
 
+    /// Like `remove`, but also reports whether the removal made the whole tree shorter.
+    #[inline]
+    pub fn remove_tracked(&mut self, val: &T) -> RemoveOutcome<T> {
+        let height = self.height();
+        let removed = self.remove(val);
+        RemoveOutcome {
+            removed,
+            height_decreased: self.height() < height,
+        }
+    }
+
     #[inline]
     pub fn delete(&mut self, val: &T) -> bool {
         let mut con = false;
         self.root = if let Some(root) = self.root.take() {
-            let (v, val) = root.delete(&val);
+            let (v, val) = root.delete(val, &mut self.rotations);
             con = v.is_some();
             val
         } else {
             None
         };
         if con {
-            self.len -= 1
+            self.len -= 1;
+            self.bump_generation();
         }
         con
     }
@@ -222,11 +917,153 @@ impl<T: Ord> AVL<T> {
         }
     }
 
+    /// Retains only the elements for which `f` returns `true`, rebuilding the remaining elements into a balanced tree.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_mut(|v| f(v))
+    }
+
+    /// Like `retain`, but also lets `f` mutate the elements it looks at.
+    ///
+    /// `f` must not change the relative order of the elements it keeps: the kept elements are reassembled
+    /// in their original positions without being compared again. This is checked in debug builds.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        let kept: Vec<T> = IntoIncreasing::new(self.root.take())
+            .filter_map(|mut v| if f(&mut v) { Some(v) } else { None })
+            .collect();
+        debug_assert!(kept.windows(2).all(|w| w[0] <= w[1]), "retain_mut changed the ordering of retained elements");
+        self.replace_sorted(kept);
+    }
+
+    /// Like `retain`, but returns the removed elements in increasing order instead of dropping them.
+    pub fn retain_collect<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> Vec<T> {
+        let (kept, removed): (Vec<T>, Vec<T>) = IntoIncreasing::new(self.root.take()).partition(|v| f(v));
+        self.replace_sorted(kept);
+        removed
+    }
+
+    /// Merges the elements of `iter`, which must already be in increasing order, into the tree with one linear merge
+    /// of both sequences, rebuilding it balanced in `O(n + m)`.
+    ///
+    /// Duplicates are kept, with incoming elements placed after the equal elements already stored, just as `insert` would
+    /// place them. The order of `iter` is checked in debug builds only.
+    pub fn merge_sorted_iter(&mut self, iter: impl IntoIterator<Item = T>) {
+        let incoming = iter.into_iter();
+        let mut merged = Vec::with_capacity(self.len + incoming.size_hint().0);
+        let mut existing = IntoIncreasing::new(self.root.take()).peekable();
+        for val in incoming {
+            while let Some(old) = existing.next_if(|old| old <= &val) {
+                merged.push(old);
+            }
+            merged.push(val);
+        }
+        merged.extend(existing);
+        debug_assert!(merged.windows(2).all(|w| w[0] <= w[1]), "merge_sorted_iter given unsorted input");
+        self.replace_sorted(merged);
+    }
+
+    /// Collapses every run of equal elements down to its first element in increasing order and rebuilds the
+    /// tree balanced, in one `O(n)` pass.
+    pub fn dedup(&mut self) {
+        let mut vals: Vec<T> = IntoIncreasing::new(self.root.take()).collect();
+        vals.dedup();
+        self.replace_sorted(vals);
+    }
+
+    /// Removes every element equal to some element of `keys`, returning how many were removed.
+    ///
+    /// Both trees are walked once in increasing order and the survivors are rebuilt into a balanced tree,
+    /// which is `O(n + m)` instead of `m` separate descents.
+    pub fn remove_all_in(&mut self, keys: &AVL<T>) -> usize {
+        let mut keys = keys.increasing().peekable();
+        let before = self.len;
+        let kept: Vec<T> = IntoIncreasing::new(self.root.take())
+            .filter(|v| {
+                while keys.next_if(|&k| k < v).is_some() {}
+                keys.peek().map(|&k| k != v).unwrap_or(true)
+            })
+            .collect();
+        self.replace_sorted(kept);
+        before - self.len
+    }
+
+    /// Walks the tree in order and checks that every element is `<=` the next according to `T::cmp`.
+    ///
+    /// The normal API always keeps the tree ordered, so `false` means the `Ord` implementation of `T` is
+    /// not a consistent total order (or an element was mutated out of place), which makes lookups unreliable.
+    pub fn verify_ordering(&self) -> bool {
+        self.increasing().zip(self.increasing().skip(1)).all(|(a, b)| a <= b)
+    }
+
+    /// Merges both trees into one balanced tree in `O(n + m)`.
+    ///
+    /// When an element of `self` and an element of `other` compare equal, only `resolve(existing, incoming)`
+    /// is kept, where `existing` comes from `self` and `incoming` from `other`.
+    pub fn union_with<F: Fn(T, T) -> T>(self, other: Self, resolve: F) -> Self {
+        let mut vals = Vec::with_capacity(self.len + other.len);
+        let mut left = self.into_increasing().peekable();
+        let mut right = other.into_increasing().peekable();
+        loop {
+            let ord = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.cmp(r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            let next = match ord {
+                Ordering::Less => left.next(),
+                Ordering::Greater => right.next(),
+                Ordering::Equal => left.next().zip(right.next()).map(|(l, r)| resolve(l, r)),
+            };
+            vals.extend(next);
+        }
+        let mut avl = Self::new();
+        avl.replace_sorted(vals);
+        avl
+    }
+
     #[inline]
     pub fn contains(&self, target: &T) -> bool {
         self.root.as_ref().map(|n| n.contains(target)).unwrap_or(false)
     }
 
+    /// Returns a reference to the stored element equal to `target`.
+    ///
+    /// This is useful when `T` carries data that takes no part in its ordering.
+    #[inline]
+    pub fn get(&self, target: &T) -> Option<&T> {
+        self.root.as_ref().and_then(|n| n.get_by(|v| target.cmp(v)))
+    }
+
+
+    /// Searches the tree for `target`, mirroring `slice::binary_search` over the increasing order.
+    ///
+    /// Returns `Ok` with the in-order index of a matching element, or `Err` with the index at which
+    /// `target` could be inserted to keep the order. Runs in `O(log(n))` using the subtree sizes.
+    #[inline]
+    pub fn binary_search(&self, target: &T) -> Result<usize, usize> {
+        self.root.as_ref().map(|r| r.binary_search(target)).unwrap_or(Err(0))
+    }
+
+    /// Returns the length of the longest run of consecutive values, where `successor` gives the value that
+    /// directly follows its argument (or `None` if there is none, such as at the type's maximum).
+    ///
+    /// Duplicates neither extend nor break a run.
+    pub fn longest_consecutive<F: Fn(&T) -> Option<T>>(&self, successor: F) -> usize {
+        let mut iter = self.increasing();
+        let Some(mut prev) = iter.next() else {
+            return 0;
+        };
+        let (mut run, mut longest) = (1, 1);
+        for cur in iter {
+            if cur == prev {
+                continue;
+            }
+            run = if successor(prev).as_ref() == Some(cur) { run + 1 } else { 1 };
+            longest = longest.max(run);
+            prev = cur;
+        }
+        longest
+    }
 
     #[inline]
     pub fn max(&self) -> Option<&T> {
@@ -239,6 +1076,18 @@ impl<T: Ord> AVL<T> {
         self.root.as_ref().map(|r| r.find_min())
     }
 
+    /// Like `min`, but reports an empty tree as an `EmptyTreeError` so it can be propagated with `?`.
+    #[inline]
+    pub fn try_min(&self) -> Result<&T, EmptyTreeError> {
+        self.min().ok_or(EmptyTreeError)
+    }
+
+    /// Like `max`, but reports an empty tree as an `EmptyTreeError` so it can be propagated with `?`.
+    #[inline]
+    pub fn try_max(&self) -> Result<&T, EmptyTreeError> {
+        self.max().ok_or(EmptyTreeError)
+    }
+
     #[inline]
     pub fn nearest_to<'a, F>(&'a self, target: &'a T, by: F) -> Option<&'a T>
     where
@@ -248,6 +1097,50 @@ impl<T: Ord> AVL<T> {
         self.root.as_ref().map(|r| r.nearest_to(target, &by))
     }
 
+    /// Returns the element nearest to `target` together with its distance as measured by `dist(target, element)`.
+    ///
+    /// `dist` should grow as elements move away from `target` in the tree order, so that the nearest
+    /// element lies on the search path for `target`, which is the only part of the tree visited.
+    #[inline]
+    pub fn nearest_with_distance<'a, D: Ord, F: Fn(&T, &T) -> D>(&'a self, target: &'a T, dist: F) -> Option<(&'a T, D)> {
+        self.root.as_ref().map(|r| r.nearest_with_distance(target, &dist))
+    }
+
+    /// Returns the greatest element `<= target` and the least element `>= target` in a single `O(log(n))` descent.
+    ///
+    /// When `target` is present, both sides of the pair are the matching element.
+    #[inline]
+    pub fn bracket(&self, target: &T) -> (Option<&T>, Option<&T>) {
+        self.root.as_ref().map(|r| r.bracket(target, None, None)).unwrap_or((None, None))
+    }
+
+    /// Returns `(predecessor, matched, successor)` around `val` in a single `O(log(n))` descent.
+    ///
+    /// When an element equal to `val` is found, its in-order neighbours surround it. Otherwise `matched` is `None`
+    /// and the other two are the greatest element below `val` and the least element above it.
+    pub fn context(&self, val: &T) -> (Option<&T>, Option<&T>, Option<&T>) {
+        let (mut pred, mut succ) = (None, None);
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            match val.cmp(&node.val) {
+                Ordering::Less => {
+                    succ = Some(&node.val);
+                    cur = node.left.as_deref();
+                }
+                Ordering::Greater => {
+                    pred = Some(&node.val);
+                    cur = node.right.as_deref();
+                }
+                Ordering::Equal => {
+                    let pred = node.left.as_ref().map(|l| l.find_max()).or(pred);
+                    let succ = node.right.as_ref().map(|r| r.find_min()).or(succ);
+                    return (pred, Some(&node.val), succ);
+                }
+            }
+        }
+        (pred, None, succ)
+    }
+
     #[inline]
     pub fn farthest_to<'a, F>(&'a self, target: &'a T, by: F) -> Option<&'a T>
     where
@@ -257,21 +1150,392 @@ impl<T: Ord> AVL<T> {
         self.root.as_ref().map(|r| r.farthest_to(target, &by))
     }
     
+    /// Walks both trees in increasing order once, categorizing every element as held by one side or by both, in `O(n + m)`.
+    ///
+    /// Equal elements pair up one to one, so when a value is repeated the surplus copies on either side show up as `OnlyLeft`
+    /// or `OnlyRight`.
+    pub fn diff<'a>(&'a self, other: &'a AVL<T>) -> impl Iterator<Item = Diff<'a, T>> {
+        let mut left = self.increasing().peekable();
+        let mut right = other.increasing().peekable();
+        std::iter::from_fn(move || match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => match l.cmp(r) {
+                Ordering::Less => left.next().map(Diff::OnlyLeft),
+                Ordering::Greater => right.next().map(Diff::OnlyRight),
+                Ordering::Equal => Some(Diff::Both(left.next()?, right.next()?)),
+            },
+            (Some(_), None) => left.next().map(Diff::OnlyLeft),
+            (None, Some(_)) => right.next().map(Diff::OnlyRight),
+            (None, None) => None,
+        })
+    }
+
+    /// Returns the elements equal to at least one of `queries`, in increasing order.
+    ///
+    /// The queries are sorted and matched against the tree in a single sweep, in `O(n + q log(q))` for `q` queries.
+    /// Each element of the tree is returned at most once, however many queries match it.
+    pub fn intersection_with_slice(&self, queries: &[T]) -> Vec<&T> {
+        let mut sorted: Vec<&T> = queries.iter().collect();
+        sorted.sort();
+        let mut queries = sorted.into_iter().peekable();
+        let mut hits = Vec::new();
+        for val in self.increasing() {
+            while queries.next_if(|q| *q < val).is_some() {}
+            match queries.peek() {
+                Some(q) if *q == val => hits.push(val),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        hits
+    }
+
+    /// Returns the number of distinct values, counting every run of equal elements once.
+    ///
+    /// Equal elements are adjacent in increasing order, so this is a single `O(n)` pass, unlike `len` which counts every copy.
+    pub fn distinct_len(&self) -> usize {
+        let mut vals = self.increasing();
+        let Some(mut prev) = vals.next() else { return 0 };
+        let mut distinct = 1;
+        for val in vals {
+            if val != prev {
+                distinct += 1;
+            }
+            prev = val;
+        }
+        distinct
+    }
+
+    /// Counts the elements falling in each of `num_buckets` buckets in one pass, where `bucket_of` gives the bucket
+    /// index of an element. Indices past the last bucket are clamped into it. With zero buckets nothing is counted.
+    pub fn histogram<B: Fn(&T) -> usize>(&self, num_buckets: usize, bucket_of: B) -> Vec<usize> {
+        let mut counts = vec![0; num_buckets];
+        if let Some(last) = num_buckets.checked_sub(1) {
+            self.increasing().for_each(|v| counts[bucket_of(v).min(last)] += 1);
+        }
+        counts
+    }
+
+    /// Returns the value with the most copies together with its multiplicity, in one `O(n)` pass over the runs of
+    /// equal elements. On a tie the smallest such value wins.
+    pub fn mode(&self) -> Option<(&T, usize)> {
+        let mut vals = self.increasing();
+        let mut run = (vals.next()?, 1);
+        let mut best = run;
+        for val in vals {
+            if val == run.0 {
+                run.1 += 1;
+            } else {
+                run = (val, 1);
+            }
+            if run.1 > best.1 {
+                best = run;
+            }
+        }
+        Some(best)
+    }
+
+    /// Returns the elements strictly greater than `lower` in increasing order, reaching the first one in `O(log(n))`.
     pub fn greater_than<'a>(&'a self, lower: &'a T) -> impl Iterator<Item = &'a T> {
-        self.increasing().skip_while(|&v| v <= lower)
+        Increasing::seek(self.root.as_deref(), |v| v <= lower)
     }
 
+    /// Returns the elements strictly less than `upper` in decreasing order, reaching the first one in `O(log(n))`.
     pub fn less_than<'a>(&'a self, upper: &'a T) -> impl Iterator<Item = &'a T> {
-        self.decreasing().skip_while(|&v| v >= upper)
+        Decreasing::seek(self.root.as_deref(), |v| v >= upper)
+    }
+
+    /// Returns every element whose distance `dist(target, element)` is at most `max_dist`, nearest first.
+    ///
+    /// The search brackets `target` in `O(log(n))` and then expands outwards from both sides, so elements come out in
+    /// increasing distance order, with ties going to the smaller element. As with `nearest_with_distance`, `dist` must
+    /// grow as elements move away from `target` in the tree order.
+    pub fn within<'a, D: Ord, F: Fn(&T, &T) -> D + 'a>(&'a self, target: &'a T, max_dist: D, dist: F) -> impl Iterator<Item = &'a T> {
+        let mut below = Decreasing::seek(self.root.as_deref(), |v| v > target).peekable();
+        let mut above = Increasing::seek(self.root.as_deref(), |v| v <= target).peekable();
+        std::iter::from_fn(move || {
+            let near_below = below.peek().map(|v| dist(target, v)).filter(|d| d <= &max_dist);
+            let near_above = above.peek().map(|v| dist(target, v)).filter(|d| d <= &max_dist);
+            match (near_below, near_above) {
+                (Some(b), Some(a)) if a < b => above.next(),
+                (Some(_), _) => below.next(),
+                (None, Some(_)) => above.next(),
+                (None, None) => None,
+            }
+        })
+    }
+
+    /// Returns the elements from the first one `>= target` onwards, in increasing order.
+    /// The first element is reached in `O(log(n))`.
+    pub fn lower_bound<'a>(&'a self, target: &'a T) -> impl Iterator<Item = &'a T> {
+        Increasing::seek(self.root.as_deref(), |v| v < target)
+    }
+
+    /// Returns the elements from the first one `> target` onwards, in increasing order.
+    /// The first element is reached in `O(log(n))`.
+    pub fn upper_bound<'a>(&'a self, target: &'a T) -> impl Iterator<Item = &'a T> {
+        Increasing::seek(self.root.as_deref(), |v| v <= target)
+    }
+
+    /// Returns every stored element equal to `target`, reaching the first one in `O(log(n))`.
+    pub fn equal_range<'a>(&'a self, target: &'a T) -> impl Iterator<Item = &'a T> {
+        self.lower_bound(target).take_while(move |&v| v == target)
+    }
+
+    /// Returns the elements from `lower` onwards in increasing order, including `lower` itself if `inclusive`.
+    /// The first element is reached in `O(log(n))`.
+    pub fn range_from<'a>(&'a self, lower: &'a T, inclusive: bool) -> impl Iterator<Item = &'a T> {
+        Increasing::seek(self.root.as_deref(), |v| if inclusive { v < lower } else { v <= lower })
+    }
+
+    /// Returns the element of `range` with the smallest `cost`, the first one in increasing order on a tie.
+    ///
+    /// The start of `range` is reached in `O(log(n))`, after which every element of the range is costed, for
+    /// `O(log(n) + k)` in total, since `cost` need not follow the tree order.
+    pub fn min_by_in_range<R: RangeBounds<T>, D: Ord, F: Fn(&T) -> D>(&self, range: R, cost: F) -> Option<&T> {
+        Increasing::seek(self.root.as_deref(), |v| before_start(&range, v))
+            .take_while(|v| !after_end(&range, v))
+            .min_by_key(|v| cost(v))
+    }
+
+    /// Consumes the tree and yields the owned elements lying in `range`, in increasing order.
+    /// Elements outside of `range` are dropped.
+    pub fn into_range<R: RangeBounds<T>>(self, range: R) -> impl Iterator<Item = T> {
+        let mut vals = IntoIncreasing::new(self.root);
+        std::iter::from_fn(move || loop {
+            let val = vals.next()?;
+            if !before_start(&range, &val) {
+                return if after_end(&range, &val) { None } else { Some(val) };
+            }
+        })
+    }
+
+    /// Returns the elements up to `upper` in increasing order, including `upper` itself if `inclusive`.
+    pub fn range_to<'a>(&'a self, upper: &'a T, inclusive: bool) -> impl Iterator<Item = &'a T> {
+        self.increasing().take_while(move |&v| if inclusive { v <= upper } else { v < upper })
+    }
+}
+
+impl<T: Ord + AsBytes> AVL<T> {
+    /// Encodes the tree as its element count (a little-endian `u64`) followed by every element in increasing
+    /// order, each written with `AsBytes::write_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + self.len * T::SIZE);
+        out.extend_from_slice(&(self.len as u64).to_le_bytes());
+        for val in self.increasing() {
+            val.write_bytes(&mut out);
+        }
+        out
+    }
+
+    /// Decodes a tree written by `to_bytes`, building it balanced in `O(n)`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError> {
+        let (header, body) = bytes.split_at_checked(8).ok_or(FromBytesError::InvalidLength)?;
+        let len = u64::from_le_bytes(header.try_into().unwrap_or_default());
+        let expected = usize::try_from(len).ok().and_then(|len| len.checked_mul(T::SIZE));
+        if expected != Some(body.len()) {
+            return Err(FromBytesError::InvalidLength);
+        }
+        let vals: Vec<T> = body.chunks_exact(T::SIZE.max(1)).take(len as usize).map(T::read_bytes).collect();
+        if vals.windows(2).any(|w| w[0] > w[1]) {
+            return Err(FromBytesError::Unsorted);
+        }
+        let mut avl = Self::new();
+        avl.replace_sorted(vals);
+        Ok(avl)
+    }
+}
+
+impl<T: Debug> AVL<T> {
+    /// Renders the tree one node per line with box-drawing connectors, left child before right child.
+    ///
+    /// Leaves end their branch, and a node with a single child marks the missing side with `·`, so the output grows with
+    /// the number of nodes rather than with `2^height` like `levels` does.
+    ///
+    /// ```text
+    /// 2
+    /// ├── 1
+    /// └── 4
+    ///     ├── ·
+    ///     └── 5
+    /// ```
+    pub fn pretty_print(&self, f: &mut impl Write) -> std::fmt::Result {
+        fn children<T: Debug>(node: &Node<T>, prefix: &mut String, f: &mut impl Write) -> std::fmt::Result {
+            if node.left.is_none() && node.right.is_none() {
+                return Ok(());
+            }
+            for (child, last) in [(&node.left, false), (&node.right, true)] {
+                let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+                match child {
+                    Some(child) => {
+                        writeln!(f, "{prefix}{branch}{:?}", child.val)?;
+                        prefix.push_str(indent);
+                        children(child, prefix, f)?;
+                        prefix.truncate(prefix.len() - indent.len());
+                    }
+                    None => writeln!(f, "{prefix}{branch}·")?,
+                }
+            }
+            Ok(())
+        }
+        match &self.root {
+            Some(root) => {
+                writeln!(f, "{:?}", root.val)?;
+                children(root, &mut String::new(), f)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Ord + Debug> AVL<T> {
+    /// Like `increasing`, but in debug builds panics with the offending pair as soon as an element compares greater
+    /// than the one after it, which points at an `Ord` implementation that is not a total order or at an element
+    /// mutated out of place. In release builds it behaves exactly like `increasing`.
+    pub fn iter_checked(&self) -> impl Iterator<Item = &T> {
+        let mut prev: Option<&T> = None;
+        self.increasing().inspect(move |&val| {
+            if cfg!(debug_assertions) {
+                if let Some(prev) = prev {
+                    assert!(prev <= val, "tree out of order: {:?} comes before {:?}", prev, val);
+                }
+                prev = Some(val);
+            }
+        })
+    }
+}
+
+impl<T: Display> AVL<T> {
+    /// Describes the tree in GraphViz DOT, one node per element labeled with its value and one edge per child link.
+    ///
+    /// Nodes are named `n0`, `n1`, ... in pre-order, so the same tree always renders to the same text. Quotes and
+    /// backslashes in the labels are escaped.
+    pub fn to_dot(&self) -> String {
+        self.write_dot(false)
+    }
+
+    /// Like `to_dot`, but also draws a point-shaped `nullN` sentinel for every missing child, which makes it
+    /// visible whether a lone child hangs to the left or to the right.
+    pub fn to_dot_with_nulls(&self) -> String {
+        self.write_dot(true)
+    }
+
+    fn write_dot(&self, null_sentinels: bool) -> String {
+        fn visit<T: Display>(node: &Node<T>, next_id: &mut usize, nulls: &mut Option<usize>, out: &mut String) -> usize {
+            let id = *next_id;
+            *next_id += 1;
+            let label = node.val.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(out, "    n{id} [label=\"{label}\"];");
+            for child in [&node.left, &node.right] {
+                match (child, nulls.as_mut()) {
+                    (Some(child), _) => {
+                        let child_id = visit(child, next_id, nulls, out);
+                        let _ = writeln!(out, "    n{id} -> n{child_id};");
+                    }
+                    (None, Some(null_id)) => {
+                        let _ = writeln!(out, "    null{null_id} [shape=point];");
+                        let _ = writeln!(out, "    n{id} -> null{null_id};");
+                        *null_id += 1;
+                    }
+                    (None, None) => {}
+                }
+            }
+            id
+        }
+        let mut out = String::from("digraph AVL {\n");
+        if let Some(root) = &self.root {
+            visit(root, &mut 0, &mut null_sentinels.then_some(0), &mut out);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl<T: Hash> AVL<T> {
+    /// Hashes the length and the elements in increasing order with a fresh `H`.
+    ///
+    /// Trees with equal contents produce equal digests regardless of their shape.
+    pub fn content_digest<H: Hasher + Default>(&self) -> u64 {
+        let mut hasher = H::default();
+        hasher.write_usize(self.len);
+        self.increasing().for_each(|v| v.hash(&mut hasher));
+        hasher.finish()
+    }
+
+    /// Builds the merkle tree over the elements, where every digest combines an element with the digests of the
+    /// elements before and after it, in `O(n)`.
+    ///
+    /// Keep the returned `MerkleTree` to compare roots repeatedly, or to locate the differing elements of two trees,
+    /// until `generation()` shows that this tree has changed.
+    pub fn merkle_tree<H: Hasher + Default>(&self) -> MerkleTree<H> {
+        MerkleTree::new(self.len, self.increasing(), self.generation)
+    }
+
+    /// Returns the root of the merkle tree built by `merkle_tree`. Two trees holding the same elements always share
+    /// a root, whatever their node layout. This rebuilds every digest in `O(n)` on each call.
+    pub fn merkle_root<H: Hasher + Default>(&self) -> u64 {
+        self.merkle_tree::<H>().root()
+    }
+}
+
+impl<T: Ord + Copy + Into<f64>> AVL<T> {
+    /// Returns the minimum, maximum, count, sum and mean of the elements from a single traversal, or `None` if the
+    /// tree is empty.
+    pub fn stats(&self) -> Option<Stats<T>> {
+        let mut vals = self.increasing().copied();
+        let min = vals.next()?;
+        let (mut max, mut count, mut sum) = (min, 1, min.into());
+        for val in vals {
+            max = val;
+            count += 1;
+            sum += val.into();
+        }
+        Some(Stats { min, max, count, sum, mean: sum / count as f64 })
+    }
+}
+
+impl<T: Ord + AsRef<str>> AVL<T> {
+    /// Returns the elements starting with `prefix` in increasing order, in `O(log(n) + k)` for `k` matches.
+    ///
+    /// The ordering of `T` must agree with the ordering of the strings it derefs to, as it does for `String`,
+    /// so that the matches form one contiguous run starting at the first element `>= prefix`.
+    pub fn iter_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a T> {
+        Increasing::seek(self.root.as_deref(), |v| v.as_ref() < prefix).take_while(move |v| v.as_ref().starts_with(prefix))
+    }
+
+    /// Returns whether some element starts with `prefix`, in `O(log(n))`.
+    pub fn any_with_prefix(&self, prefix: &str) -> bool {
+        self.iter_with_prefix(prefix).next().is_some()
     }
 }
 
 impl<T: Ord + Nearness> AVL<T> {
+    /// Returns the element nearest to `target`. For circular types such as `Wrapping` integers this also compares
+    /// the smallest and the largest element, which can be nearest across the wraparound point.
     #[inline]
     pub fn nearest<'a>(&'a self, target: &'a T) -> Option<&'a T> {
-        self.root
+        let found = self
+            .root
             .as_ref()
-            .map(|r| r.nearest_to(target, &move |a, b| T::nearer(a, b, target)))
+            .map(|r| r.nearest_to(target, &move |a, b| T::nearer(a, b, target)))?;
+        if !T::CIRCULAR {
+            return Some(found);
+        }
+        Some([self.min(), self.max()].into_iter().flatten().fold(found, |best, end| T::nearer(end, best, target)))
+    }
+
+    /// Like `nearest`, but also reports whether the returned element is an exact match for `target`.
+    #[inline]
+    pub fn nearest_kind<'a>(&'a self, target: &'a T) -> Option<(NearestKind, &'a T)> {
+        match self.get(target) {
+            Some(found) => Some((NearestKind::Exact, found)),
+            None => self.nearest(target).map(|near| (NearestKind::Approximate, near)),
+        }
+    }
+
+    /// Like `nearest`, but reports an empty tree as an `EmptyTreeError` so it can be propagated with `?`.
+    #[inline]
+    pub fn try_nearest<'a>(&'a self, target: &'a T) -> Result<&'a T, EmptyTreeError> {
+        self.nearest(target).ok_or(EmptyTreeError)
     }
 
     #[inline]
@@ -282,6 +1546,14 @@ impl<T: Ord + Nearness> AVL<T> {
     }
 }
 
+impl<T: PartialEq> PartialEq for AVL<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.increasing().eq(other.increasing())
+    }
+}
+
+impl<T: Eq> Eq for AVL<T> {}
+
 impl<T> IntoIterator for AVL<T> {
     type IntoIter = IntoIter<T>;
     type Item = T;
@@ -301,3 +1573,6 @@ impl<T: Ord> FromIterator<T> for AVL<T> {
         avl
     }
 }
+
+#[cfg(test)]
+mod tests;
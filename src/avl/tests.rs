@@ -0,0 +1,1472 @@
+use super::*;
+use crate::Pair;
+use std::collections::hash_map::DefaultHasher;
+
+/// A small deterministic pseudo-random generator so that the randomized tests are reproducible
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+/// A record ordered by `id` alone, so that `ttl` is extra data the tree does not look at
+#[derive(Debug, Clone)]
+struct Rec {
+    id: u32,
+    ttl: u32,
+}
+
+impl PartialEq for Rec {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Rec {}
+
+impl PartialOrd for Rec {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rec {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+fn rec(id: u32, ttl: u32) -> Rec {
+    Rec { id, ttl }
+}
+
+#[test]
+fn try_for_each_stops_at_first_err() {
+    let tree: AVL<i32> = (0..10).collect();
+    let mut visited = 0;
+    let res = tree.try_for_each(|&v| {
+        visited += 1;
+        if v == 2 {
+            Err(v)
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(res, Err(2));
+    assert_eq!(visited, 3);
+
+    let mut visited = 0;
+    assert_eq!(
+        tree.try_for_each(|_| {
+            visited += 1;
+            Ok::<(), ()>(())
+        }),
+        Ok(())
+    );
+    assert_eq!(visited, 10);
+    assert_eq!(AVL::<i32>::new().try_for_each(|_| Err(())), Ok(()));
+}
+
+#[test]
+fn find_returns_first_match_in_order() {
+    let tree: AVL<i32> = (0..100).collect();
+    assert_eq!(tree.find(|&x| x > 50), Some(&51));
+    assert_eq!(tree.find(|&x| x > 500), None);
+    assert_eq!(
+        tree.find_map(|&x| if x * x > 50 { Some(x * 10) } else { None }),
+        Some(80)
+    );
+    assert_eq!(AVL::<i32>::new().find(|_| true), None);
+}
+
+#[test]
+fn binary_search_reports_rank_or_gap() {
+    let tree: AVL<i32> = (0..50).map(|x| x * 2).collect();
+    assert_eq!(tree.binary_search(&40), Ok(20));
+    assert_eq!(tree.binary_search(&41), Err(21));
+    assert_eq!(tree.binary_search(&-1), Err(0));
+    assert_eq!(tree.binary_search(&99), Err(50));
+
+    let sorted: Vec<i32> = (0..50).map(|x| x * 2).collect();
+    for q in -2..105 {
+        assert_eq!(tree.binary_search(&q), sorted.binary_search(&q), "{q}");
+    }
+    assert_eq!(AVL::<i32>::new().binary_search(&3), Err(0));
+}
+
+#[test]
+fn subtree_sizes_survive_churn() {
+    let mut state = 7;
+    let mut tree = AVL::new();
+    let mut reference = std::collections::BTreeSet::new();
+    for _ in 0..3000 {
+        let x = (lcg(&mut state) % 500) as i32;
+        if lcg(&mut state).is_multiple_of(3) {
+            tree.remove(&x);
+            reference.remove(&x);
+        } else {
+            tree.insert_distinct(x);
+            reference.insert(x);
+        }
+    }
+    let sorted: Vec<i32> = reference.into_iter().collect();
+    assert_eq!(tree.len(), sorted.len());
+    for q in -1..502 {
+        assert_eq!(tree.binary_search(&q), sorted.binary_search(&q));
+    }
+}
+
+#[test]
+fn equal_values_keep_the_tree_shallow() {
+    let mut tree = AVL::new();
+    for _ in 0..100_000 {
+        tree.insert(7u8);
+    }
+    assert_eq!(tree.len(), 100_000);
+    assert!(tree.height() <= 18, "height {}", tree.height());
+    assert!(tree.is_within_bound());
+
+    let mut multi = AVL::new();
+    for x in [3, 1, 3, 2, 3, 1] {
+        multi.insert(x);
+    }
+    assert!(multi.increasing().copied().eq([1, 1, 2, 3, 3, 3]));
+}
+
+#[test]
+fn levels_match_height() {
+    let tree: AVL<i32> = (0..7).collect();
+    assert_eq!(tree.height(), 3);
+    assert_eq!(tree.levels().count(), 3);
+    let widths: Vec<usize> = tree.levels().map(|l| l.count()).collect();
+    assert_eq!(widths, vec![1, 2, 4]);
+
+    assert_eq!(AVL::<i32>::new().levels().count(), 0);
+    let single: AVL<i32> = [1].into_iter().collect();
+    assert_eq!(single.levels().count(), 1);
+}
+
+#[test]
+fn generation_tracks_modifications_only() {
+    let mut tree = AVL::new();
+    let g0 = tree.generation();
+    tree.insert(1);
+    let g1 = tree.generation();
+    assert!(g1 > g0);
+    tree.insert_distinct(2);
+    let g2 = tree.generation();
+    assert!(g2 > g1);
+
+    let _ = (
+        tree.contains(&1),
+        tree.len(),
+        tree.min(),
+        tree.increasing().count(),
+        tree.find(|_| true),
+    );
+    assert_eq!(tree.generation(), g2);
+    assert!(tree.remove(&5).is_none());
+    assert_eq!(tree.generation(), g2);
+
+    tree.remove(&1);
+    let g3 = tree.generation();
+    assert!(g3 > g2);
+    tree.clear();
+    assert!(tree.generation() > g3);
+}
+
+#[test]
+fn generation_counts_mutable_access_to_elements() {
+    let mut tree: AVL<i32> = (0..10).collect();
+    let mut last = tree.generation();
+    let mut bumped = |tree: &AVL<i32>| {
+        let now = tree.generation();
+        let moved = now != last;
+        last = now;
+        moved
+    };
+    *tree.nth_mut(3).unwrap() = 3;
+    assert!(bumped(&tree));
+    tree.find_by_mut(|v| 4.cmp(v));
+    assert!(bumped(&tree));
+    tree.fold_mut((), |_, _| ());
+    assert!(bumped(&tree));
+    tree.recount();
+    assert!(bumped(&tree));
+    tree.fix_heights();
+    assert!(bumped(&tree));
+    let _ = (tree.nth_from_start(3), tree.find_by(|v| 4.cmp(v)));
+    assert!(!bumped(&tree));
+    assert!(tree.nth_mut(10).is_none());
+    assert!(tree.find_by_mut(|v| 40.cmp(v)).is_none());
+    AVL::<i32>::new().fold_mut((), |_, _| ());
+    assert!(!bumped(&tree));
+}
+
+#[test]
+fn retain_mut_mutates_and_filters() {
+    let mut tree: AVL<Rec> = (0..10).map(|id| rec(id, id % 3 + 1)).collect();
+    tree.retain_mut(|e| {
+        e.ttl -= 1;
+        e.ttl > 0
+    });
+    let left: Vec<(u32, u32)> = tree.increasing().map(|e| (e.id, e.ttl)).collect();
+    assert_eq!(left, vec![(1, 1), (2, 2), (4, 1), (5, 2), (7, 1), (8, 2)]);
+    assert_eq!(tree.len(), 6);
+    assert_eq!(tree.height(), 3);
+}
+
+#[test]
+fn remove_all_in_subtracts_a_key_set() {
+    let mut tree: AVL<i32> = (1..=5).collect();
+    let keys: AVL<i32> = [2, 4, 9].into_iter().collect();
+    assert_eq!(tree.remove_all_in(&keys), 2);
+    assert!(tree.increasing().copied().eq([1, 3, 5]));
+    assert_eq!(tree.len(), 3);
+
+    let mut multi = AVL::new();
+    for x in [1, 2, 2, 3] {
+        multi.insert(x);
+    }
+    assert_eq!(multi.remove_all_in(&[2].into_iter().collect()), 2);
+    assert!(multi.increasing().copied().eq([1, 3]));
+}
+
+#[test]
+fn nearest_with_distance_matches_dist() {
+    let tree: AVL<i64> = (0..40).map(|x| x * x).collect();
+    for q in -5..1700i64 {
+        let (v, d) = tree
+            .nearest_with_distance(&q, |a, b| a.abs_diff(*b))
+            .unwrap();
+        assert_eq!(d, q.abs_diff(*v));
+        assert!(tree.increasing().all(|x| x.abs_diff(q) >= d));
+    }
+    assert!(AVL::<i64>::new()
+        .nearest_with_distance(&1, |a, b| a.abs_diff(*b))
+        .is_none());
+}
+
+#[test]
+fn bracket_straddles_gaps() {
+    let tree: AVL<i32> = (0..30).map(|x| x * 10).collect();
+    assert_eq!(tree.bracket(&45), (Some(&40), Some(&50)));
+    assert_eq!(tree.bracket(&50), (Some(&50), Some(&50)));
+    assert_eq!(tree.bracket(&-1), (None, Some(&0)));
+    assert_eq!(tree.bracket(&291), (Some(&290), None));
+    for q in -3..300 {
+        let floor = tree.increasing().filter(|&&x| x <= q).last();
+        let ceil = tree.increasing().find(|&&x| x >= q);
+        assert_eq!(tree.bracket(&q), (floor, ceil));
+    }
+}
+
+#[test]
+fn insert_unchecked_sorted_builds_the_same_tree() {
+    let mut a = AVL::new();
+    let mut b = AVL::new();
+    for x in 0..10_000u64 {
+        a.insert(x);
+        b.insert_unchecked_sorted(x);
+    }
+    assert_eq!(a.len(), b.len());
+    assert_eq!(a.height(), b.height());
+    assert!(a.levels().zip(b.levels()).all(|(x, y)| x.eq(y)));
+}
+
+#[test]
+fn get_returns_the_stored_element() {
+    let tree: AVL<Rec> = (0..10).map(|id| rec(id, id * 7)).collect();
+    assert_eq!(tree.get(&rec(3, 0)).unwrap().ttl, 21);
+    assert!(tree.get(&rec(30, 0)).is_none());
+}
+
+#[test]
+fn positional_access() {
+    let empty = AVL::<i32>::new();
+    assert_eq!((empty.first(), empty.last()), (None, None));
+    assert_eq!(
+        (empty.nth_from_start(0), empty.nth_from_end(0)),
+        (None, None)
+    );
+    let single: AVL<i32> = [4].into_iter().collect();
+    assert_eq!((single.first(), single.last()), (Some(&4), Some(&4)));
+
+    let tree: AVL<i32> = (0..100).collect();
+    for i in 0..100 {
+        assert_eq!(tree.nth_from_start(i), Some(&(i as i32)));
+        assert_eq!(tree.nth_from_end(i), Some(&(99 - i as i32)));
+    }
+    assert_eq!(tree.nth_from_start(100), None);
+    assert_eq!(tree.nth_from_end(100), None);
+    assert_eq!(tree.nth_from_end(usize::MAX), None);
+}
+
+#[test]
+fn rotation_count_sorted_inserts_vs_bulk_load() {
+    let n = 1000;
+    let mut tree = AVL::new();
+    for x in 1..=n {
+        tree.insert(x);
+    }
+    let rotations = tree.rotation_count();
+    assert!(rotations > 900 && rotations < 1100, "{rotations}");
+
+    let mut bulk = AVL::new();
+    bulk.replace_sorted((1..=n).collect());
+    assert_eq!(bulk.rotation_count(), 0);
+    assert_eq!(bulk.height(), tree.height());
+    assert_eq!(AVL::<i32>::new().rotation_count(), 0);
+}
+
+#[test]
+fn structurally_eq_compares_shape() {
+    let a: AVL<i32> = [1, 2, 3, 4].into_iter().collect();
+    let b: AVL<i32> = [4, 3, 2, 1].into_iter().collect();
+    assert_eq!(a, b);
+    assert!(!a.structurally_eq(&b));
+    assert!(a.structurally_eq(&a.clone()));
+    assert!(AVL::<i32>::new().structurally_eq(&AVL::new()));
+}
+
+#[test]
+fn longest_consecutive_run() {
+    let tree: AVL<i64> = [1, 2, 3, 7, 8].into_iter().collect();
+    assert_eq!(tree.longest_consecutive(|x| x.checked_add(1)), 3);
+
+    let mut multi = AVL::new();
+    for x in [5, 5, 6, 6, 7, 1] {
+        multi.insert(x);
+    }
+    assert_eq!(multi.longest_consecutive(|x: &i64| x.checked_add(1)), 3);
+    assert_eq!(
+        AVL::<i64>::new().longest_consecutive(|x| x.checked_add(1)),
+        0
+    );
+    let top: AVL<u8> = [254, 255].into_iter().collect();
+    assert_eq!(top.longest_consecutive(|x| x.checked_add(1)), 2);
+}
+
+#[test]
+fn half_ranges_against_a_reference() {
+    let tree: AVL<i32> = (0..40).map(|x| x * 3).collect();
+    let sorted: Vec<i32> = tree.increasing().copied().collect();
+    for q in -2..125 {
+        let above = |inclusive| {
+            sorted
+                .iter()
+                .copied()
+                .filter(move |&x| if inclusive { x >= q } else { x > q })
+        };
+        let below = |inclusive| {
+            sorted
+                .iter()
+                .copied()
+                .filter(move |&x| if inclusive { x <= q } else { x < q })
+        };
+        assert!(tree.range_from(&q, true).copied().eq(above(true)));
+        assert!(tree.range_from(&q, false).copied().eq(above(false)));
+        assert!(tree.range_to(&q, true).copied().eq(below(true)));
+        assert!(tree.range_to(&q, false).copied().eq(below(false)));
+        assert!(tree.greater_than(&q).copied().eq(above(false)));
+    }
+
+    let mut multi = AVL::new();
+    for x in [2, 2, 2, 1, 3] {
+        multi.insert(x);
+    }
+    assert_eq!(multi.range_from(&2, true).count(), 4);
+    assert_eq!(multi.range_to(&2, false).count(), 1);
+}
+
+#[test]
+fn bytes_round_trip() {
+    let tree: AVL<u64> = (0..1000).map(|x| x * 7919 % 1000).collect();
+    let bytes = tree.to_bytes();
+    assert_eq!(bytes.len(), 8 + 8000);
+    let back = AVL::<u64>::from_bytes(&bytes).unwrap();
+    assert_eq!(tree, back);
+    assert_eq!(back.height(), 10);
+    assert_eq!(
+        AVL::<i8>::from_bytes(&AVL::<i8>::new().to_bytes())
+            .unwrap()
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn from_bytes_rejects_bad_input() {
+    let bytes = AVL::<u64>::from_iter(0..10).to_bytes();
+    assert_eq!(
+        AVL::<u64>::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(FromBytesError::InvalidLength)
+    );
+    assert_eq!(
+        AVL::<u64>::from_bytes(&bytes[..3]),
+        Err(FromBytesError::InvalidLength)
+    );
+
+    let mut unsorted = 2u64.to_le_bytes().to_vec();
+    unsorted.extend(5u64.to_le_bytes());
+    unsorted.extend(1u64.to_le_bytes());
+    assert_eq!(
+        AVL::<u64>::from_bytes(&unsorted),
+        Err(FromBytesError::Unsorted)
+    );
+}
+
+#[test]
+fn recount_repairs_len() {
+    let mut tree: AVL<i32> = (0..321).collect();
+    tree.remove(&5);
+    assert_eq!(tree.recount(), 320);
+    assert_eq!(tree.len(), 320);
+
+    tree.len = 7;
+    assert_eq!(tree.recount(), 320);
+    assert_eq!(tree.len(), 320);
+    assert_eq!(AVL::<i32>::new().recount(), 0);
+}
+
+#[test]
+fn iter_from_rank_starts_mid_tree() {
+    let tree: AVL<i32> = (0..1000).collect();
+    let mut it = tree.iter_from_rank(500);
+    assert_eq!(it.size_hint(), (500, Some(500)));
+    assert_eq!(it.next(), Some(&500));
+    for start in [0, 1, 499, 999, 1000, 1001] {
+        assert!(tree.iter_from_rank(start).eq(tree.increasing().skip(start)));
+    }
+    assert_eq!(AVL::<i32>::new().iter_from_rank(3).count(), 0);
+
+    let ranked: Vec<_> = tree.enumerate_sorted().collect();
+    assert_eq!(ranked.len(), 1000);
+    assert_eq!(ranked[7], (7, &7));
+    assert_eq!(tree.enumerate_sorted().size_hint(), (1000, Some(1000)));
+}
+
+#[test]
+fn nth_mut_updates_in_place() {
+    let mut tree: AVL<Rec> = (0..11).map(|id| rec(id, 0)).collect();
+    tree.nth_mut(5).unwrap().ttl = 99;
+    let median = tree.nth_from_start(5).unwrap();
+    assert_eq!((median.id, median.ttl), (5, 99));
+    assert!(tree.nth_mut(11).is_none());
+}
+
+#[test]
+fn height_stays_within_the_avl_bound() {
+    let empty = AVL::<i32>::new();
+    assert_eq!(empty.max_possible_height(), 0);
+    assert!(empty.is_within_bound());
+    let bounds: Vec<usize> = [1, 2, 3, 4, 7, 12, 20, 33]
+        .iter()
+        .map(|&n| (0..n).collect::<AVL<i32>>().max_possible_height())
+        .collect();
+    assert_eq!(bounds, vec![1, 2, 2, 3, 4, 5, 6, 7]);
+
+    let mut state = 3;
+    let mut tree = AVL::new();
+    for i in 0..5000 {
+        let x = (lcg(&mut state) % 3000) as i32;
+        if lcg(&mut state).is_multiple_of(4) {
+            tree.remove(&x);
+        } else {
+            tree.insert(x);
+        }
+        if i % 50 == 0 {
+            assert!(tree.is_within_bound());
+        }
+    }
+}
+
+#[test]
+fn into_range_yields_owned_values() {
+    let tree: AVL<String> = (0..40).map(|x| format!("{x:02}")).collect();
+    let window: Vec<String> = tree
+        .into_range("10".to_string().."20".to_string())
+        .collect();
+    assert_eq!(
+        window,
+        (10..20).map(|x| format!("{x:02}")).collect::<Vec<_>>()
+    );
+
+    let tree: AVL<i32> = (0..40).collect();
+    assert_eq!(
+        tree.clone().into_range(..=3).collect::<Vec<_>>(),
+        vec![0, 1, 2, 3]
+    );
+    assert_eq!(tree.into_range(38..).collect::<Vec<_>>(), vec![38, 39]);
+}
+
+#[test]
+fn dedup_collapses_runs() {
+    let mut tree = AVL::new();
+    for x in [1, 1, 2, 3, 3, 3] {
+        tree.insert(x);
+    }
+    tree.dedup();
+    assert_eq!(tree.len(), 3);
+    assert!(tree.increasing().copied().eq([1, 2, 3]));
+}
+
+#[test]
+fn verify_ordering_flags_broken_trees() {
+    let mut tree: AVL<Rec> = (0..50).map(|id| rec(id, 0)).collect();
+    assert!(tree.verify_ordering());
+    tree.nth_mut(3).unwrap().id = 40;
+    assert!(!tree.verify_ordering());
+    assert!(AVL::<i32>::new().verify_ordering());
+}
+
+#[test]
+fn union_with_resolves_collisions() {
+    let a: AVL<Pair<i32, i32>> = [(1, 10), (2, 20), (3, 30)]
+        .into_iter()
+        .map(|(k, v)| Pair::new(k, v))
+        .collect();
+    let b: AVL<Pair<i32, i32>> = [(2, 2), (3, 3), (4, 4)]
+        .into_iter()
+        .map(|(k, v)| Pair::new(k, v))
+        .collect();
+    let merged = a.union_with(b, |x, y| Pair::new(x.key, x.val + y.val));
+    let entries: Vec<_> = merged.increasing().map(|p| (p.key, p.val)).collect();
+    assert_eq!(entries, vec![(1, 10), (2, 22), (3, 33), (4, 4)]);
+    assert_eq!(merged.len(), 4);
+}
+
+#[test]
+fn equal_range_yields_every_copy() {
+    let mut tree = AVL::new();
+    for x in [5, 1, 5, 9, 5, 2, 7] {
+        tree.insert(x);
+    }
+    assert_eq!(tree.equal_range(&5).count(), 3);
+    assert_eq!(tree.equal_range(&6).count(), 0);
+    assert_eq!(tree.equal_range(&9).count(), 1);
+}
+
+#[test]
+fn lower_and_upper_bounds() {
+    let tree: AVL<i32> = [10, 20, 30].into_iter().collect();
+    assert!(tree.lower_bound(&20).copied().eq([20, 30]));
+    assert!(tree.upper_bound(&20).copied().eq([30]));
+    assert!(tree.lower_bound(&25).copied().eq([30]));
+    assert!(tree.upper_bound(&25).copied().eq([30]));
+    assert_eq!(tree.upper_bound(&30).count(), 0);
+    assert_eq!(tree.lower_bound(&0).count(), 3);
+}
+
+#[test]
+fn retain_collect_returns_the_removed() {
+    let mut tree: AVL<i32> = (0..10).collect();
+    assert_eq!(tree.retain_collect(|x| x % 2 == 0), vec![1, 3, 5, 7, 9]);
+    assert!(tree.increasing().copied().eq([0, 2, 4, 6, 8]));
+    assert_eq!(tree.len(), 5);
+}
+
+#[test]
+fn insert_status_reports_overwrites() {
+    let mut tree = AVL::new();
+    assert_eq!(tree.insert_status(rec(1, 10)), InsertStatus::Inserted);
+    match tree.insert_status(rec(1, 20)) {
+        InsertStatus::Overwritten(old) => assert_eq!(old.ttl, 10),
+        InsertStatus::Inserted => panic!("the key was already present"),
+    }
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree.min().unwrap().ttl, 20);
+    assert!(tree.insert_distinct(rec(2, 0)));
+    assert!(!tree.insert_distinct(rec(2, 1)));
+}
+
+#[test]
+fn find_by_projected_key() {
+    let mut tree: AVL<Rec> = (0..20).map(|id| rec(id, 0)).collect();
+    assert_eq!(tree.find_by(|r| 7.cmp(&r.id)).unwrap().id, 7);
+    tree.find_by_mut(|r| 7.cmp(&r.id)).unwrap().ttl = 3;
+    assert_eq!(tree.find_by(|r| 7.cmp(&r.id)).unwrap().ttl, 3);
+    assert!(tree.find_by(|r| 70.cmp(&r.id)).is_none());
+}
+
+#[test]
+fn remove_tracked_reports_height_decrease() {
+    // 7 is the only leaf on the deepest level
+    let mut tree: AVL<i32> = (0..8).collect();
+    assert_eq!(tree.height(), 4);
+    assert_eq!(
+        tree.remove_tracked(&7),
+        RemoveOutcome {
+            removed: Some(7),
+            height_decreased: true
+        }
+    );
+    assert_eq!(
+        tree.remove_tracked(&0),
+        RemoveOutcome {
+            removed: Some(0),
+            height_decreased: false
+        }
+    );
+    assert_eq!(
+        tree.remove_tracked(&70),
+        RemoveOutcome {
+            removed: None,
+            height_decreased: false
+        }
+    );
+}
+
+#[test]
+fn compact_preserves_contents_and_balances() {
+    let mut state = 7;
+    let mut tree = AVL::new();
+    for _ in 0..5000 {
+        tree.insert(lcg(&mut state) % 1000);
+    }
+    for _ in 0..3000 {
+        let v = lcg(&mut state) % 1000;
+        tree.remove(&v);
+    }
+    let before: Vec<u64> = tree.increasing().copied().collect();
+    let generation = tree.generation();
+    tree.compact();
+    assert!(tree.increasing().copied().eq(before.iter().copied()));
+    assert_eq!(tree.len(), before.len());
+    assert!(tree.is_within_bound());
+    assert!(tree.height() <= (usize::BITS - before.len().leading_zeros()) as usize);
+    assert_ne!(tree.generation(), generation);
+
+    let mut empty: AVL<u8> = AVL::new();
+    empty.compact();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn try_accessors_on_empty_and_populated_trees() {
+    fn sum(tree: &AVL<i32>) -> Result<i32, Box<dyn std::error::Error>> {
+        Ok(*tree.try_min()? + *tree.try_max()? + *tree.try_nearest(&4)?)
+    }
+    let empty: AVL<i32> = AVL::new();
+    assert_eq!(empty.try_min(), Err(EmptyTreeError));
+    assert_eq!(empty.try_max(), Err(EmptyTreeError));
+    assert_eq!(empty.try_nearest(&1), Err(EmptyTreeError));
+    assert!(sum(&empty).is_err());
+    assert_eq!(EmptyTreeError.to_string(), "the tree is empty");
+
+    let tree: AVL<i32> = [3, 1, 6].into_iter().collect();
+    assert_eq!(tree.try_min(), Ok(&1));
+    assert_eq!(tree.try_max(), Ok(&6));
+    assert_eq!(sum(&tree).unwrap(), 1 + 6 + 3);
+}
+
+#[test]
+fn scan_yields_running_state() {
+    let tree: AVL<i32> = [3, 1, 2].into_iter().collect();
+    let sums = tree.scan(0, |s, v| *s += v);
+    assert_eq!(sums.len(), 3);
+    assert_eq!(sums.collect::<Vec<_>>(), vec![1, 3, 6]);
+    let maxima: Vec<i32> = tree.scan(i32::MIN, |m, v| *m = (*m).max(-v)).collect();
+    assert_eq!(maxima, vec![-1, -1, -1]);
+    assert_eq!(AVL::<i32>::new().scan(0, |_, _| {}).count(), 0);
+}
+
+#[test]
+fn pop_first_and_last_keep_balance() {
+    let mut state = 3;
+    let mut tree = AVL::new();
+    let mut reference = std::collections::BTreeSet::new();
+    for _ in 0..2000 {
+        let x = lcg(&mut state) % 700;
+        tree.insert_distinct(x);
+        reference.insert(x);
+    }
+    while !reference.is_empty() {
+        if lcg(&mut state).is_multiple_of(2) {
+            assert_eq!(tree.pop_first(), reference.pop_first());
+        } else {
+            assert_eq!(tree.pop_last(), reference.pop_last());
+        }
+        assert!(tree.is_within_bound());
+        assert_eq!(tree.len(), reference.len());
+    }
+    assert_eq!(tree.pop_first(), None);
+    assert_eq!(tree.pop_last(), None);
+}
+
+#[test]
+fn digests_depend_on_contents_only() {
+    let a: AVL<u32> = (0..500).collect();
+    let b: AVL<u32> = (0..500).rev().collect();
+    assert!(!a.structurally_eq(&b));
+    assert_eq!(
+        a.merkle_root::<DefaultHasher>(),
+        b.merkle_root::<DefaultHasher>()
+    );
+    assert_eq!(
+        a.content_digest::<DefaultHasher>(),
+        b.content_digest::<DefaultHasher>()
+    );
+
+    let mut c = a.clone();
+    c.remove(&250);
+    c.insert(1000);
+    assert_ne!(
+        a.merkle_root::<DefaultHasher>(),
+        c.merkle_root::<DefaultHasher>()
+    );
+    assert_ne!(
+        a.content_digest::<DefaultHasher>(),
+        c.content_digest::<DefaultHasher>()
+    );
+    assert_ne!(
+        AVL::<u32>::new().merkle_root::<DefaultHasher>(),
+        a.merkle_root::<DefaultHasher>()
+    );
+}
+
+#[test]
+fn merkle_tree_locates_the_changed_elements() {
+    let mut a: AVL<u32> = (0..1000).map(|v| v * 3).collect();
+    let before = a.merkle_tree::<DefaultHasher>();
+    assert_eq!(before.root(), a.merkle_root::<DefaultHasher>());
+    assert_eq!(before.generation(), a.generation());
+    assert_eq!(before.diverging_ranks(&a.merkle_tree()), Some(vec![]));
+
+    for rank in [0, 417, 999] {
+        *a.nth_mut(rank).unwrap() += 1;
+    }
+    let after = a.merkle_tree::<DefaultHasher>();
+    assert_ne!(after.generation(), before.generation());
+    assert_ne!(after.root(), before.root());
+    assert_eq!(before.diverging_ranks(&after), Some(vec![0, 417, 999]));
+
+    a.pop_last();
+    assert_eq!(before.diverging_ranks(&a.merkle_tree()), None);
+    let empty = AVL::<u32>::new().merkle_tree::<DefaultHasher>();
+    assert!(empty.is_empty());
+    assert_eq!(
+        empty.root(),
+        AVL::<u32>::new().merkle_root::<DefaultHasher>()
+    );
+}
+
+#[test]
+fn iter_into_buf_reuses_one_stack() {
+    let mut state = 11;
+    let tree: AVL<u64> = (0..3000).map(|_| lcg(&mut state) % 900).collect();
+    let want: Vec<u64> = tree.increasing().copied().collect();
+    let mut stack = TraversalStack::new();
+    for _ in 0..5 {
+        assert!(tree
+            .iter_into_buf(&mut stack)
+            .copied()
+            .eq(want.iter().copied()));
+    }
+    // an abandoned traversal leaves entries behind, which the next one clears
+    tree.iter_into_buf(&mut stack).next();
+    assert!(tree
+        .iter_into_buf(&mut stack)
+        .copied()
+        .eq(want.iter().copied()));
+
+    let empty: AVL<u64> = AVL::new();
+    let mut stack = TraversalStack::default();
+    assert_eq!(empty.iter_into_buf(&mut stack).count(), 0);
+}
+
+#[test]
+fn within_returns_exactly_the_radius() {
+    let mut state = 5;
+    let tree: AVL<i64> = (0..2000).map(|_| (lcg(&mut state) % 1000) as i64).collect();
+    for target in [-10i64, 0, 37, 500, 999, 1200] {
+        for radius in [0u64, 1, 5, 40] {
+            let got: Vec<i64> = tree
+                .within(&target, radius, |a, b| a.abs_diff(*b))
+                .copied()
+                .collect();
+            assert!(got
+                .windows(2)
+                .all(|w| w[0].abs_diff(target) <= w[1].abs_diff(target)));
+            let mut got = got;
+            got.sort();
+            let want: Vec<i64> = tree
+                .increasing()
+                .copied()
+                .filter(|v| v.abs_diff(target) <= radius)
+                .collect();
+            assert_eq!(got, want);
+        }
+    }
+}
+
+#[test]
+fn distinct_len_collapses_duplicates() {
+    let tree: AVL<i32> = [1, 1, 2, 2, 2, 3].into_iter().collect();
+    assert_eq!((tree.distinct_len(), tree.len()), (3, 6));
+    assert_eq!(AVL::<i32>::new().distinct_len(), 0);
+    let same: AVL<i32> = [7; 40].into_iter().collect();
+    assert_eq!(same.distinct_len(), 1);
+}
+
+#[test]
+fn pretty_print_golden() {
+    let tree: AVL<i32> = [2, 1, 4, 5].into_iter().collect();
+    let mut out = String::new();
+    tree.pretty_print(&mut out).unwrap();
+    assert_eq!(out, "2\n├── 1\n└── 4\n    ├── ·\n    └── 5\n");
+
+    let tree: AVL<i32> = (1..=7).collect();
+    let mut out = String::new();
+    tree.pretty_print(&mut out).unwrap();
+    let golden = "\
+4
+├── 2
+│   ├── 1
+│   └── 3
+└── 6
+    ├── 5
+    └── 7
+";
+    assert_eq!(out, golden);
+
+    let mut out = String::new();
+    AVL::<u8>::new().pretty_print(&mut out).unwrap();
+    assert!(out.is_empty());
+}
+
+#[test]
+fn pretty_print_scales_with_the_node_count() {
+    let tree: AVL<u32> = (0..1000).collect();
+    let mut out = String::new();
+    tree.pretty_print(&mut out).unwrap();
+    assert!(out.lines().count() < 2 * tree.len());
+    assert_eq!(
+        out.lines().filter(|l| !l.ends_with('·')).count(),
+        tree.len()
+    );
+}
+
+thread_local! {
+    static COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// An element that counts how often it is compared
+#[derive(PartialEq, Eq)]
+struct Counted(u64);
+
+impl PartialOrd for Counted {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Counted {
+    fn cmp(&self, other: &Self) -> Ordering {
+        COMPARISONS.with(|c| c.set(c.get() + 1));
+        self.0.cmp(&other.0)
+    }
+}
+
+fn comparisons(f: impl FnOnce()) -> usize {
+    COMPARISONS.with(|c| c.set(0));
+    f();
+    COMPARISONS.with(|c| c.get())
+}
+
+#[test]
+fn insert_ref_points_into_the_tree() {
+    let mut tree = AVL::new();
+    for (id, ttl) in [(5, 1), (2, 2), (5, 3), (9, 4)] {
+        let r = tree.insert_ref(rec(id, ttl));
+        assert_eq!((r.id, r.ttl), (id, ttl));
+    }
+    assert_eq!(tree.len(), 3);
+    let r: *const Rec = tree.insert_ref(rec(7, 0));
+    assert!(tree.increasing().any(|v| std::ptr::eq(v, r)));
+}
+
+#[test]
+fn insert_ref_compares_along_one_path() {
+    let mut tree: AVL<Counted> = (0..1023).map(|v| Counted(v * 2)).collect();
+    let height = tree.height();
+    for v in [501, 500] {
+        let used = comparisons(|| assert_eq!(tree.insert_ref(Counted(v)).0, v));
+        assert!(used <= height + 1, "{used} comparisons at height {height}");
+    }
+    assert_eq!(tree.len(), 1024);
+    assert!(tree.is_balanced());
+}
+
+#[test]
+fn diff_categorizes_in_order() {
+    let a: AVL<i32> = [1, 2, 3].into_iter().collect();
+    let b: AVL<i32> = [2, 3, 4].into_iter().collect();
+    let diff: Vec<_> = a.diff(&b).collect();
+    assert_eq!(
+        diff,
+        vec![
+            Diff::OnlyLeft(&1),
+            Diff::Both(&2, &2),
+            Diff::Both(&3, &3),
+            Diff::OnlyRight(&4)
+        ]
+    );
+    let empty = AVL::new();
+    assert_eq!(a.diff(&empty).count(), 3);
+    assert_eq!(empty.diff(&empty).count(), 0);
+}
+
+#[test]
+fn take_returns_the_stored_element() {
+    let mut tree: AVL<Rec> = [(1, 10), (4, 40), (9, 90)]
+        .into_iter()
+        .map(|(id, ttl)| rec(id, ttl))
+        .collect();
+    let taken = tree.take(&rec(4, 0)).unwrap();
+    assert_eq!((taken.id, taken.ttl), (4, 40));
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.take_by(|v| 9.cmp(&v.id)).unwrap().ttl, 90);
+    assert!(tree.take(&rec(4, 0)).is_none());
+    assert!(tree.take_by(|v| 3.cmp(&v.id)).is_none());
+    assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn context_around_keys_and_gaps() {
+    let tree: AVL<i32> = (0..100).map(|x| x * 2).collect();
+    assert_eq!(tree.context(&40), (Some(&38), Some(&40), Some(&42)));
+    assert_eq!(tree.context(&41), (Some(&40), None, Some(&42)));
+    assert_eq!(tree.context(&0), (None, Some(&0), Some(&2)));
+    assert_eq!(tree.context(&198), (Some(&196), Some(&198), None));
+    assert_eq!(tree.context(&-5), (None, None, Some(&0)));
+    assert_eq!(tree.context(&500), (Some(&198), None, None));
+    for x in -1..200 {
+        let (pred, matched, succ) = tree.context(&x);
+        assert_eq!(pred, tree.less_than(&x).next());
+        assert_eq!(matched, tree.get(&x));
+        assert_eq!(succ, tree.greater_than(&x).next());
+    }
+    assert_eq!(AVL::<i32>::new().context(&1), (None, None, None));
+}
+
+#[test]
+fn insert_if_absent_matches_a_set() {
+    let mut state = 9;
+    let mut tree = AVL::new();
+    let mut reference = std::collections::BTreeSet::new();
+    for _ in 0..3000 {
+        let x = lcg(&mut state) % 800;
+        assert_eq!(tree.insert_if_absent(x), reference.insert(x));
+    }
+    assert!(tree.increasing().eq(reference.iter()));
+    assert!(tree.is_within_bound());
+    assert_eq!(tree.len(), reference.len());
+}
+
+#[test]
+fn to_dot_declares_nodes_and_edges() {
+    let tree: AVL<i32> = [2, 1, 3].into_iter().collect();
+    let dot = tree.to_dot();
+    for line in [
+        "digraph AVL {",
+        "n0 [label=\"2\"];",
+        "n1 [label=\"1\"];",
+        "n2 [label=\"3\"];",
+        "n0 -> n1;",
+        "n0 -> n2;",
+    ] {
+        assert!(dot.contains(line), "{line}");
+    }
+    assert!(!dot.contains("shape=point"));
+    assert_eq!(tree.to_dot_with_nulls().matches("[shape=point]").count(), 4);
+    assert_eq!(AVL::<i32>::new().to_dot(), "digraph AVL {\n}\n");
+
+    let quoted: AVL<String> = ["a\"b".to_string()].into_iter().collect();
+    assert!(quoted.to_dot().contains(r#"n0 [label="a\"b"];"#));
+}
+
+#[test]
+fn collecting_preallocates() {
+    let mut state = 2;
+    let tree: AVL<u64> = (0..1000).map(|_| lcg(&mut state) % 5000).collect();
+    let want: Vec<u64> = tree.increasing().copied().collect();
+    let refs: Vec<&u64> = tree.collect_refs();
+    assert_eq!(refs.capacity(), 1000);
+    let set = tree.clone().into_btreeset();
+    assert!(set
+        .iter()
+        .eq(want.iter().collect::<std::collections::BTreeSet<_>>()));
+    let deque: std::collections::VecDeque<u64> = tree.clone().collect_into();
+    assert!(deque.iter().eq(want.iter()));
+    let vec = tree.into_vec();
+    assert_eq!(vec.capacity(), 1000);
+    assert_eq!(vec, want);
+    assert!(AVL::<u8>::new().into_vec().is_empty());
+}
+
+#[test]
+fn mode_prefers_the_smallest_on_ties() {
+    let tree: AVL<i32> = [1, 2, 2, 3, 3, 3].into_iter().collect();
+    assert_eq!(tree.mode(), Some((&3, 3)));
+    let tied: AVL<i32> = [5, 2, 5, 2, 9].into_iter().collect();
+    assert_eq!(tied.mode(), Some((&2, 2)));
+    let distinct: AVL<i32> = [4, 1, 7].into_iter().collect();
+    assert_eq!(distinct.mode(), Some((&1, 1)));
+    assert_eq!(AVL::<i32>::new().mode(), None);
+}
+
+#[test]
+fn stride_samples_every_step() {
+    let tree: AVL<i32> = (0..10).collect();
+    assert!(tree.stride(2).copied().eq([0, 2, 4, 6, 8]));
+    assert!(tree.stride(3).copied().eq([0, 3, 6, 9]));
+    assert_eq!(tree.stride(3).len(), 4);
+    assert!(tree.stride(1).copied().eq(0..10));
+    assert_eq!(tree.stride(100).count(), 1);
+    assert_eq!(tree.stride(usize::MAX).count(), 1);
+    assert_eq!(AVL::<i32>::new().stride(2).len(), 0);
+}
+
+#[test]
+#[should_panic]
+fn stride_rejects_zero() {
+    let tree: AVL<i32> = (0..10).collect();
+    let _ = tree.stride(0).count();
+}
+
+#[test]
+fn from_unsorted_vec_matches_collect() {
+    let mut state = 77;
+    let vals: Vec<u64> = (0..20_000).map(|_| lcg(&mut state) % 10_000).collect();
+    let collected: AVL<u64> = vals.iter().copied().collect();
+    let bulk = AVL::from_unsorted_vec(vals.clone());
+    assert!(collected.increasing().eq(bulk.increasing()));
+    assert_eq!(bulk.len(), vals.len());
+    assert!(bulk.is_within_bound());
+    assert_eq!(bulk.rotation_count(), 0);
+
+    let distinct = AVL::from_unsorted_vec_distinct(vals.clone());
+    let want: std::collections::BTreeSet<u64> = vals.into_iter().collect();
+    assert!(distinct.increasing().eq(want.iter()));
+    assert_eq!(distinct.len(), want.len());
+
+    // the first of several equal elements is the one kept
+    let first = AVL::from_unsorted_vec_distinct(vec![rec(1, 1), rec(0, 0), rec(1, 2)]);
+    assert_eq!(first.max().unwrap().ttl, 1);
+}
+
+#[test]
+fn try_map_rebuilds_or_fails_early() {
+    let tree: AVL<String> = ["10", "-3", "7"].into_iter().map(String::from).collect();
+    let parsed = tree.try_map(|s| s.parse::<i64>()).unwrap();
+    assert!(parsed.increasing().copied().eq([-3, 7, 10]));
+    assert!(parsed.is_within_bound());
+
+    let bad: AVL<String> = ["1", "x", "2", "y"].into_iter().map(String::from).collect();
+    let mut seen = 0;
+    let res = bad.try_map(|s| {
+        seen += 1;
+        s.parse::<i64>()
+    });
+    assert!(res.is_err());
+    assert_eq!(seen, 3);
+}
+
+#[test]
+fn prefix_queries() {
+    let tree: AVL<String> = ["ant", "ape", "apple", "axe", "a"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert!(tree
+        .iter_with_prefix("ap")
+        .map(|s| s.as_str())
+        .eq(["ape", "apple"]));
+    assert!(tree.any_with_prefix("ax"));
+    assert!(!tree.any_with_prefix("b"));
+    assert!(!tree.any_with_prefix("apples"));
+    assert_eq!(tree.iter_with_prefix("").count(), 5);
+}
+
+#[test]
+fn split_into_balanced_chunks() {
+    let tree: AVL<i32> = (0..100).collect();
+    let parts = tree.split_into(4);
+    assert_eq!(
+        parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+        vec![25; 4]
+    );
+    assert!(parts
+        .iter()
+        .all(|p| p.is_within_bound() && p.verify_ordering()));
+    assert!(parts
+        .iter()
+        .flat_map(|p| p.increasing().copied())
+        .eq(0..100));
+
+    let parts = AVL::from_iter(0..10).split_into(3);
+    assert_eq!(
+        parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+        vec![4, 3, 3]
+    );
+    let parts = AVL::from_iter(0..2).split_into(4);
+    assert_eq!(
+        parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+        vec![1, 1, 0, 0]
+    );
+}
+
+#[test]
+fn fold_mut_accumulates_and_mutates() {
+    let mut tree: AVL<i64> = [3, 1, 2].into_iter().collect();
+    let total = tree.fold_mut(0, |acc, v| {
+        let acc = acc + *v;
+        *v *= 10;
+        acc
+    });
+    assert_eq!(total, 6);
+    assert!(tree.increasing().copied().eq([10, 20, 30]));
+    let order = tree.fold_mut(Vec::new(), |mut acc, v| {
+        acc.push(*v);
+        acc
+    });
+    assert_eq!(order, vec![10, 20, 30]);
+    assert_eq!(AVL::<i64>::new().fold_mut(7, |a, _| a + 1), 7);
+}
+
+#[test]
+fn interpolate_at_fractional_ranks() {
+    let tree: AVL<i32> = [40, 10, 30, 20].into_iter().collect();
+    assert_eq!(tree.interpolate_at(0.5), Some((&20, &30, 0.5)));
+    assert_eq!(tree.interpolate_at(0.0), Some((&10, &10, 0.0)));
+    assert_eq!(tree.interpolate_at(1.0), Some((&40, &40, 0.0)));
+    let (lo, hi, w) = tree.interpolate_at(1.0 / 3.0).unwrap();
+    assert_eq!((lo, hi), (&20, &20));
+    assert!(w.abs() < 1e-9);
+    assert_eq!(tree.interpolate_at(1.5), None);
+    assert_eq!(tree.interpolate_at(f64::NAN), None);
+    assert_eq!(AVL::<i32>::new().interpolate_at(0.5), None);
+    let single: AVL<i32> = [7].into_iter().collect();
+    assert_eq!(single.interpolate_at(0.3), Some((&7, &7, 0.0)));
+}
+
+#[test]
+fn fix_heights_repairs_zeroed_fields() {
+    fn zero(node: &mut Node<u64>) {
+        node.height = 0;
+        node.size = 0;
+        for child in [&mut node.left, &mut node.right].into_iter().flatten() {
+            zero(child);
+        }
+    }
+    let mut state = 4;
+    let mut tree: AVL<u64> = (0..3000).map(|_| lcg(&mut state) % 900).collect();
+    for _ in 0..1000 {
+        let v = lcg(&mut state) % 900;
+        tree.remove(&v);
+    }
+    assert!(tree.is_balanced());
+    let height = tree.height();
+
+    zero(tree.root.as_mut().unwrap());
+    assert!(!tree.is_balanced());
+    tree.fix_heights();
+    assert!(tree.is_balanced());
+    assert_eq!(tree.height(), height);
+    assert!(AVL::<u8>::new().is_balanced());
+}
+
+#[test]
+fn first_where_is_logarithmic() {
+    let tree: AVL<i32> = (0..100).collect();
+    let mut calls = 0;
+    let found = tree.first_where(|&x| {
+        calls += 1;
+        x >= 50
+    });
+    assert_eq!(found, Some(&50));
+    assert!(calls <= tree.height(), "{calls}");
+    assert_eq!(tree.first_where(|&x| x >= 0), Some(&0));
+    assert_eq!(tree.first_where(|&x| x > 99), None);
+    assert_eq!(AVL::<i32>::new().first_where(|_| true), None);
+}
+
+#[test]
+fn subtrees_expose_sizes_and_heights() {
+    let tree: AVL<i32> = (0..100).collect();
+    let infos: Vec<_> = tree.subtrees().collect();
+    assert_eq!(infos.len(), 100);
+    assert_eq!(tree.subtrees().len(), 100);
+    assert_eq!(infos[0].size, tree.len());
+    assert_eq!(infos[0].height as usize, tree.height());
+    assert!(infos.iter().all(|i| i.size >= 1 && i.height >= 1));
+    let leaves = infos.iter().filter(|i| i.size == 1).count();
+    assert_eq!(leaves, infos.iter().filter(|i| i.height == 1).count());
+    let mut vals: Vec<i32> = infos.iter().map(|i| *i.root_value).collect();
+    vals.sort();
+    assert!(vals.into_iter().eq(0..100));
+    assert_eq!(AVL::<i32>::new().subtrees().count(), 0);
+}
+
+#[test]
+fn iter_checked_passes_valid_trees() {
+    let tree: AVL<i32> = (0..20).collect();
+    assert!(tree.iter_checked().copied().eq(0..20));
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "out of order"))]
+fn iter_checked_panics_on_a_corrupted_tree() {
+    let mut tree: AVL<i32> = (0..20).collect();
+    tree.root.as_mut().unwrap().left.as_mut().unwrap().val = 100;
+    tree.iter_checked().for_each(drop);
+}
+
+#[test]
+fn merge_sorted_iter_equals_inserting() {
+    let mut state = 8;
+    let base: Vec<u64> = (0..5000).map(|_| lcg(&mut state) % 20_000).collect();
+    let mut batch: Vec<u64> = (0..5000).map(|_| lcg(&mut state) % 20_000).collect();
+    batch.sort();
+    let mut inserted = AVL::from_unsorted_vec(base);
+    let mut merged = inserted.clone();
+    for &x in &batch {
+        inserted.insert(x);
+    }
+    merged.merge_sorted_iter(batch);
+    assert!(inserted.increasing().eq(merged.increasing()));
+    assert_eq!(merged.len(), 10_000);
+    assert!(merged.is_balanced());
+
+    // existing elements come before equal incoming ones
+    let mut tree: AVL<Rec> = [rec(1, 0), rec(3, 0)].into_iter().collect();
+    tree.merge_sorted_iter([rec(1, 1), rec(2, 1)]);
+    let entries: Vec<_> = tree.increasing().map(|v| (v.id, v.ttl)).collect();
+    assert_eq!(entries, vec![(1, 0), (1, 1), (2, 1), (3, 0)]);
+}
+
+#[test]
+fn nearest_kind_tells_exact_from_approximate() {
+    let tree: AVL<i32> = [10, 20, 30].into_iter().collect();
+    assert_eq!(tree.nearest_kind(&20), Some((NearestKind::Exact, &20)));
+    assert_eq!(
+        tree.nearest_kind(&22),
+        Some((NearestKind::Approximate, &20))
+    );
+    assert_eq!(AVL::<i32>::new().nearest_kind(&1), None);
+}
+
+#[test]
+fn stats_in_one_pass() {
+    let tree: AVL<i32> = [3, 1, 4, 2].into_iter().collect();
+    assert_eq!(
+        tree.stats(),
+        Some(Stats {
+            min: 1,
+            max: 4,
+            count: 4,
+            sum: 10.0,
+            mean: 2.5
+        })
+    );
+    assert_eq!(AVL::<u8>::new().stats(), None);
+    let single: AVL<u8> = [9].into_iter().collect();
+    assert_eq!(single.stats().unwrap().mean, 9.0);
+}
+
+#[test]
+fn intersection_with_slice_returns_hits() {
+    let tree: AVL<i32> = (1..=5).collect();
+    assert_eq!(tree.intersection_with_slice(&[2, 4, 6]), vec![&2, &4]);
+    assert_eq!(tree.intersection_with_slice(&[6, 4, 2]), vec![&2, &4]);
+    assert_eq!(tree.intersection_with_slice(&[2, 2, 9]), vec![&2]);
+    let multi: AVL<i32> = [1, 3, 3].into_iter().collect();
+    assert_eq!(multi.intersection_with_slice(&[3]), vec![&3, &3]);
+    assert!(tree.intersection_with_slice(&[]).is_empty());
+}
+
+#[test]
+fn insert_at_cursor_matches_insert() {
+    let mut state = 6;
+    let mut a: AVL<u64> = (0..2000).map(|_| lcg(&mut state) % 10_000).collect();
+    let mut b = a.clone();
+    let mut cursor = a.cursor(&5000);
+    assert!(a.is_cursor_valid(&cursor));
+    assert_eq!(cursor.rank(), a.increasing().filter(|&&v| v < 5000).count());
+    for v in (5000..5050).chain([5020, 5020, 4999]) {
+        a.insert_at_cursor(&mut cursor, v);
+        b.insert(v);
+        assert_eq!(cursor.rank(), a.increasing().filter(|&&x| x <= v).count());
+    }
+    assert!(a.increasing().eq(b.increasing()));
+
+    let mut empty = AVL::new();
+    let mut cursor = empty.cursor(&0);
+    empty.insert_at_cursor(&mut cursor, 4);
+    empty.insert_at_cursor(&mut cursor, 5);
+    assert!(empty.increasing().copied().eq([4, 5]));
+}
+
+#[test]
+fn insert_at_cursor_compares_only_against_the_neighbours() {
+    let mut tree: AVL<Counted> = (0..1000).map(|v| Counted(v * 10)).collect();
+    let mut cursor = tree.cursor(&Counted(5001));
+    let run = comparisons(|| {
+        for v in 5001..5009 {
+            tree.insert_at_cursor(&mut cursor, Counted(v));
+        }
+    });
+    assert!(
+        run <= 8 * 2,
+        "{run} comparisons for 8 inserts at the cursor"
+    );
+    let mut cursor = tree.cursor(&Counted(100_000));
+    let appended = comparisons(|| {
+        for v in 100_000..100_100 {
+            tree.insert_at_cursor(&mut cursor, Counted(v));
+        }
+    });
+    assert!(
+        appended <= 100,
+        "{appended} comparisons for 100 appends at the cursor"
+    );
+    assert!(tree.is_balanced());
+    assert_eq!(cursor.rank(), tree.len());
+    assert!(tree
+        .increasing()
+        .zip(tree.increasing().skip(1))
+        .all(|(a, b)| a.0 < b.0));
+
+    // a value that does not belong at the cursor still lands where `insert` would put it
+    let mut cursor = tree.cursor(&Counted(0));
+    tree.insert_at_cursor(&mut cursor, Counted(5005));
+    assert_eq!(
+        cursor.rank(),
+        tree.increasing().filter(|v| v.0 <= 5005).count()
+    );
+    assert!(tree.is_balanced());
+}
+
+#[test]
+fn stale_cursors_are_rejected() {
+    let mut a: AVL<u64> = (0..10).collect();
+    let b = a.clone();
+    let stale = a.cursor(&1);
+    a.insert(3);
+    assert!(!a.is_cursor_valid(&stale));
+    assert!(!a.is_cursor_valid(&b.cursor(&1)));
+}
+
+#[test]
+#[should_panic(expected = "cursor does not point into the current state of this tree")]
+fn insert_at_a_stale_cursor_panics() {
+    let mut tree: AVL<u64> = (0..10).collect();
+    let mut stale = tree.cursor(&1);
+    tree.insert(3);
+    tree.insert_at_cursor(&mut stale, 9);
+}
+
+#[test]
+fn min_by_in_range_matches_a_linear_scan() {
+    let mut state = 12;
+    let tree: AVL<u64> = (0..2000).map(|_| lcg(&mut state) % 5000).collect();
+    let cost = |v: &u64| (v * 2654435761) % 1009;
+    for (lo, hi) in [
+        (0u64, 5000u64),
+        (100, 300),
+        (2500, 2501),
+        (4990, 6000),
+        (7000, 8000),
+    ] {
+        let want = tree
+            .increasing()
+            .filter(|&&v| v >= lo && v < hi)
+            .min_by_key(|v| cost(v));
+        assert_eq!(tree.min_by_in_range(lo..hi, cost), want);
+        let want = tree
+            .increasing()
+            .filter(|&&v| v >= lo && v <= hi)
+            .min_by_key(|v| cost(v));
+        assert_eq!(tree.min_by_in_range(lo..=hi, cost), want);
+    }
+    assert_eq!(tree.min_by_in_range(.., |v| *v), tree.min());
+}
+
+#[test]
+fn range_by_index_slices_positions() {
+    let tree: AVL<i32> = (0..10).collect();
+    assert!(tree.range_by_index(2, 5).copied().eq([2, 3, 4]));
+    assert_eq!(tree.range_by_index(2, 5).len(), 3);
+    assert!(tree.range_by_index(8, 100).copied().eq([8, 9]));
+    assert_eq!(tree.range_by_index(5, 3).count(), 0);
+    assert_eq!(tree.range_by_index(10, 20).count(), 0);
+    assert_eq!(tree.range_by_index(0, 10).count(), 10);
+}
+
+#[test]
+fn imbalance_report_lists_leaning_nodes() {
+    let complete = AVL::from_unsorted_vec((0..1023).collect::<Vec<i32>>());
+    assert!(complete.imbalance_report().is_empty());
+
+    let mut state = 1;
+    let random: AVL<u64> = (0..1000).map(|_| lcg(&mut state)).collect();
+    let report = random.imbalance_report();
+    assert!(!report.is_empty());
+    assert!(report.iter().all(|(_, bf)| bf.abs() == 1));
+    assert!(report.windows(2).all(|w| w[0].0 <= w[1].0));
+}
+
+#[test]
+fn remove_nth_zero_drains_in_order() {
+    let mut tree: AVL<i32> = [5, 3, 8, 1, 9, 2].into_iter().collect();
+    let mut drained = vec![];
+    while let Some(v) = tree.remove_nth(0) {
+        drained.push(v);
+        assert!(tree.is_balanced());
+    }
+    assert_eq!(drained, vec![1, 2, 3, 5, 8, 9]);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn remove_nth_at_random_positions() {
+    let mut state = 13;
+    let mut tree: AVL<u64> = (0..3000).map(|_| lcg(&mut state) % 1000).collect();
+    let mut sorted: Vec<u64> = tree.increasing().copied().collect();
+    while !sorted.is_empty() {
+        let i = (lcg(&mut state) as usize) % sorted.len();
+        assert_eq!(tree.remove_nth(i), Some(sorted.remove(i)));
+        assert_eq!(tree.len(), sorted.len());
+    }
+    assert!(tree.is_balanced());
+    assert_eq!(tree.remove_nth(0), None);
+
+    let mut tree: AVL<i32> = (0..5).collect();
+    assert_eq!(tree.remove_nth(5), None);
+    assert_eq!(tree.len(), 5);
+}
+
+#[test]
+fn histogram_counts_per_bucket() {
+    let tree: AVL<usize> = (0..100).collect();
+    assert_eq!(tree.histogram(10, |v| v / 10), vec![10; 10]);
+    // out of range buckets are clamped into the last one
+    assert_eq!(tree.histogram(3, |v| v / 10), vec![10, 10, 80]);
+    assert!(tree.histogram(0, |v| *v).is_empty());
+}
+
+#[test]
+fn take_all_hands_off_the_contents() {
+    let mut tree: AVL<i32> = (0..50).rev().collect();
+    let generation = tree.generation();
+    let taken = tree.take_all();
+    assert!(tree.is_empty());
+    assert_ne!(tree.generation(), generation);
+    assert!(taken.increasing().copied().eq(0..50));
+    assert_eq!(taken.len(), 50);
+    assert!(taken.is_balanced());
+    tree.insert(3);
+    assert_eq!(tree.len(), 1);
+}
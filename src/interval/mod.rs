@@ -0,0 +1,195 @@
+//! This module contains an interval tree built as an augmented AVL tree.
+//!
+//! Intervals are closed, ordered by their start (then end), and every node additionally caches the
+//! greatest end point found in its subtree so that overlap queries can skip whole subtrees.
+
+use std::cmp::Ordering;
+
+use crate::node::AvlNode;
+
+struct IntervalNode<T> {
+    height: i32,
+    interval: (T, T),
+    max_end: T,
+    left: Option<Box<IntervalNode<T>>>,
+    right: Option<Box<IntervalNode<T>>>,
+}
+
+impl<T: Ord + Clone> IntervalNode<T> {
+    fn new(interval: (T, T)) -> Self {
+        IntervalNode {
+            height: 1,
+            max_end: interval.1.clone(),
+            interval,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn insert(self: &mut Box<Self>, interval: (T, T)) {
+        let child = match interval.cmp(&self.interval) {
+            Ordering::Less => &mut self.left,
+            Ordering::Equal | Ordering::Greater => &mut self.right,
+        };
+        if let Some(child) = child {
+            child.insert(interval);
+        } else {
+            *child = Some(Box::new(IntervalNode::new(interval)));
+        }
+        self.update();
+        // the interval tree keeps no rotation count
+        self.balance(&mut 0);
+    }
+}
+
+impl<T: Ord + Clone> AvlNode for IntervalNode<T> {
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn left(&self) -> &Option<Box<Self>> {
+        &self.left
+    }
+
+    fn right(&self) -> &Option<Box<Self>> {
+        &self.right
+    }
+
+    fn left_mut(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.left
+    }
+
+    fn right_mut(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.right
+    }
+
+    /// Recomputes the cached height and greatest end point of this node from its children
+    fn update(&mut self) {
+        self.height = 1 + i32::max(
+            self.left.as_ref().map(|l| l.height).unwrap_or(0),
+            self.right.as_ref().map(|r| r.height).unwrap_or(0),
+        );
+        let mut max_end = &self.interval.1;
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            if child.max_end > *max_end {
+                max_end = &child.max_end;
+            }
+        }
+        self.max_end = max_end.clone();
+    }
+}
+
+/// ## Description
+///
+/// An interval tree stores closed intervals `(start, end)` and answers which of them overlap a query interval
+/// in `O(log(n) + k)`, where `k` is the number of reported intervals.
+///
+/// It is an AVL tree ordered by interval start in which each node also caches the greatest end point of its
+/// subtree. That cached value is maintained through every rotation and lets queries prune subtrees whose
+/// intervals all end before the query begins.
+pub struct IntervalTree<T> {
+    root: Option<Box<IntervalNode<T>>>,
+    len: usize,
+}
+
+impl<T> Default for IntervalTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> IntervalTree<T> {
+    /// Creates and returns a new, empty interval tree
+    #[inline]
+    pub fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+
+    /// Returns the number of intervals stored in this tree
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.root.as_ref().map(|r| r.height as usize).unwrap_or(0)
+    }
+}
+
+impl<T: Ord + Clone> IntervalTree<T> {
+    /// Inserts the closed interval `(start, end)`, keeping duplicates. `start` must not exceed `end`.
+    #[inline]
+    pub fn insert(&mut self, interval: (T, T)) {
+        debug_assert!(interval.0 <= interval.1, "interval start exceeds its end");
+        if let Some(root) = &mut self.root {
+            root.insert(interval);
+        } else {
+            self.root = Some(Box::new(IntervalNode::new(interval)));
+        }
+        self.len += 1;
+    }
+
+    /// Returns the stored intervals that share at least one point with the closed interval `query`,
+    /// in increasing order of start.
+    #[inline]
+    pub fn overlapping(&self, query: (T, T)) -> impl Iterator<Item = &(T, T)> {
+        let mut iter = Overlapping { stack: Vec::new(), query };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<(T, T)> for IntervalTree<T> {
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        for interval in iter {
+            tree.insert(interval)
+        }
+        tree
+    }
+}
+
+struct Overlapping<'a, T> {
+    stack: Vec<&'a IntervalNode<T>>,
+    query: (T, T),
+}
+
+impl<'a, T: Ord> Overlapping<'a, T> {
+    /// Descends the left spine of `node`, stopping at the first subtree whose intervals all end before the query
+    fn push_left(&mut self, mut node: Option<&'a IntervalNode<T>>) {
+        while let Some(n) = node {
+            if n.max_end < self.query.0 {
+                break;
+            }
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a, T: Ord> Iterator for Overlapping<'a, T> {
+    type Item = &'a (T, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            if node.interval.0 > self.query.1 {
+                // every interval still to be visited starts after the query ends
+                self.stack.clear();
+                return None;
+            }
+            self.push_left(node.right.as_deref());
+            if node.interval.1 >= self.query.0 {
+                return Some(&node.interval);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,71 @@
+use super::*;
+
+/// A small deterministic pseudo-random generator so that the randomized tests are reproducible
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+#[test]
+fn overlapping_matches_a_linear_scan() {
+    let mut state = 99;
+    let mut intervals = vec![];
+    for _ in 0..500 {
+        let start = (lcg(&mut state) % 1000) as i32;
+        let len = (lcg(&mut state) % 60) as i32;
+        intervals.push((start, start + len));
+    }
+    let tree: IntervalTree<i32> = intervals.iter().cloned().collect();
+    assert_eq!(tree.len(), 500);
+    assert!(tree.height() <= 12);
+
+    for _ in 0..200 {
+        let start = (lcg(&mut state) % 1100) as i32 - 50;
+        let query = (start, start + (lcg(&mut state) % 40) as i32);
+        let mut got: Vec<_> = tree.overlapping(query).cloned().collect();
+        assert!(got.windows(2).all(|w| w[0] <= w[1]));
+        let mut want: Vec<_> = intervals
+            .iter()
+            .cloned()
+            .filter(|&(a, b)| a <= query.1 && query.0 <= b)
+            .collect();
+        got.sort();
+        want.sort();
+        assert_eq!(got, want);
+    }
+}
+
+#[test]
+fn overlapping_known_intervals() {
+    let tree: IntervalTree<i32> = [(1, 5), (10, 15), (4, 12)].into_iter().collect();
+    assert_eq!(tree.overlapping((6, 9)).collect::<Vec<_>>(), vec![&(4, 12)]);
+    assert_eq!(tree.overlapping((5, 10)).count(), 3);
+    assert_eq!(tree.overlapping((16, 20)).count(), 0);
+}
+
+#[test]
+fn rotations_keep_the_cached_end_points() {
+    fn check(node: &Option<Box<IntervalNode<u64>>>) -> (i32, u64) {
+        let Some(node) = node else {
+            return (0, 0);
+        };
+        let (lh, lmax) = check(&node.left);
+        let (rh, rmax) = check(&node.right);
+        assert!((lh - rh).abs() <= 1);
+        assert_eq!(node.height, 1 + lh.max(rh));
+        assert_eq!(node.max_end, node.interval.1.max(lmax).max(rmax));
+        (node.height, node.max_end)
+    }
+    let ascending: IntervalTree<u64> = (0..1000).map(|s| (s, s + (s * 7919) % 500)).collect();
+    check(&ascending.root);
+    let mut state = 3;
+    let random: IntervalTree<u64> = (0..1000)
+        .map(|_| {
+            let s = lcg(&mut state) % 10_000;
+            (s, s + lcg(&mut state) % 300)
+        })
+        .collect();
+    check(&random.root);
+}
@@ -1,31 +1,46 @@
 mod avl;
 mod node;
-use std::cmp::Ordering;
+use std::{cmp::Ordering, num::Wrapping};
 
 use node::*;
 
 mod map;
 pub use map::*;
 
+mod interval;
+pub use interval::*;
+
+mod multiset;
+pub use multiset::*;
+
+mod persistent;
+pub use persistent::*;
+
 pub use avl::*;
 
 pub trait Nearness {
+    /// Whether distances wrap around, so that the smallest and the largest values can be close to each other.
+    /// `AVL::nearest` then also weighs the extremes of the tree, which the search path for a target need not visit.
+    const CIRCULAR: bool = false;
+
     fn nearer<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self;
     fn farther<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self;
 }
 
-macro_rules! impl_nearer_signed {
+/// Distances are measured with `abs_diff`, which returns the unsigned counterpart of the type and therefore
+/// can neither overflow nor underflow, whichever side of `target` the compared values sit on.
+macro_rules! impl_nearness {
     ($tp:ty) => {
         impl Nearness for $tp {
             fn nearer<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self {
-                if <$tp>::abs(self - target) < <$tp>::abs(other - target) {
+                if self.abs_diff(*target) < other.abs_diff(*target) {
                     self
                 } else {
                     other
                 }
             }
             fn farther<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self {
-                if <$tp>::abs(self - target) > <$tp>::abs(other - target) {
+                if self.abs_diff(*target) > other.abs_diff(*target) {
                     self
                 } else {
                     other
@@ -42,6 +57,12 @@ pub struct Pair<K, V> {
     pub val: V,
 }
 
+impl<K, V> Pair<K, V> {
+    pub fn new(key: K, val: V) -> Self {
+        Self { key, val }
+    }
+}
+
 impl<K: Ord, V> PartialEq for Pair<K, V> {
     fn eq(&self, other: &Self) -> bool {
         matches!(self.key.cmp(&other.key), Ordering::Equal)
@@ -60,79 +81,60 @@ impl<K: Ord, V> Ord for Pair<K, V> {
 
 impl<K: Ord, V> PartialOrd for Pair<K, V> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.key.cmp(&other.key))
+        Some(self.cmp(other))
     }
 }
 
-macro_rules! impl_nearer_unsigned {
+/// For `Wrapping` integers the distance is circular: the shorter of the forward and the backward gap
+/// modulo `2^bits`, so that for `u16` the values `65534` and `2` are only 4 apart.
+///
+/// Trees still order `Wrapping` values linearly, so a candidate that is close only across the wraparound point
+/// is always the smallest or the largest element; `CIRCULAR` tells `AVL::nearest` to weigh those as well.
+macro_rules! impl_nearness_wrapping {
     ($tp:ty) => {
-        impl Nearness for $tp {
+        impl Nearness for Wrapping<$tp> {
+            const CIRCULAR: bool = true;
+
             fn nearer<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self {
-                if self < target && other < target {
-                    if self > other {
-                        self
-                    } else {
-                        other
-                    }
-                } else if self > target && other > target {
-                    if self < other {
-                        self
-                    } else {
-                        other
-                    }
+                let dist = |v: &Self| <$tp>::min((v - target).0, (target - v).0);
+                if dist(self) < dist(other) {
+                    self
                 } else {
-                    let (larger, smaller) = if self > target {
-                        (self, other)
-                    } else {
-                        (other, self)
-                    };
-                    if larger - target < target - smaller {
-                        larger
-                    } else {
-                        smaller
-                    }
+                    other
                 }
             }
             fn farther<'a>(&'a self, other: &'a Self, target: &Self) -> &'a Self {
-                if self < target && other < target {
-                    if self > other {
-                        other
-                    } else {
-                        self
-                    }
-                } else if self > target && other > target {
-                    if self < other {
-                        other
-                    } else {
-                        self
-                    }
+                let dist = |v: &Self| <$tp>::min((v - target).0, (target - v).0);
+                if dist(self) > dist(other) {
+                    self
                 } else {
-                    let (larger, smaller) = if self > target {
-                        (self, other)
-                    } else {
-                        (other, self)
-                    };
-                    if larger - target > target - smaller {
-                        larger
-                    } else {
-                        smaller
-                    }
+                    other
                 }
             }
         }
     };
 }
 
-impl_nearer_signed!(isize);
-impl_nearer_signed!(i128);
-impl_nearer_signed!(i64);
-impl_nearer_signed!(i32);
-impl_nearer_signed!(i16);
-impl_nearer_signed!(i8);
-
-impl_nearer_unsigned!(usize);
-impl_nearer_unsigned!(u128);
-impl_nearer_unsigned!(u64);
-impl_nearer_unsigned!(u32);
-impl_nearer_unsigned!(u16);
-impl_nearer_unsigned!(u8);
+impl_nearness!(isize);
+impl_nearness!(i128);
+impl_nearness!(i64);
+impl_nearness!(i32);
+impl_nearness!(i16);
+impl_nearness!(i8);
+
+impl_nearness!(usize);
+impl_nearness!(u128);
+impl_nearness!(u64);
+impl_nearness!(u32);
+impl_nearness!(u16);
+impl_nearness!(u8);
+
+impl_nearness_wrapping!(usize);
+impl_nearness_wrapping!(u128);
+impl_nearness_wrapping!(u64);
+impl_nearness_wrapping!(u32);
+impl_nearness_wrapping!(u16);
+impl_nearness_wrapping!(u8);
+
+#[cfg(test)]
+mod tests;
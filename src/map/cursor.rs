@@ -0,0 +1,59 @@
+use std::ops::Bound;
+
+use crate::Pair;
+
+use super::BTreeMap;
+
+/// A cursor over the entries of a `BTreeMap` whose keys lie in a range, created by `BTreeMap::range_cursor`.
+///
+/// The cursor either points at an entry of the range or rests on one of two ghost positions, just before the first
+/// entry and just after the last. Every move locates the entry by rank in `O(log(n))`.
+pub struct RangeCursor<'a, K, V> {
+    map: &'a BTreeMap<K, V>,
+    /// Ranks of the first entry of the range and of the first entry after it
+    start: usize,
+    end: usize,
+    /// The rank pointed at plus one, so that `start` is the ghost before the range and `end + 1` the ghost after it
+    pos: usize,
+}
+
+impl<'a, K: Ord, V> RangeCursor<'a, K, V> {
+    pub(crate) fn new(map: &'a BTreeMap<K, V>, start: Bound<&K>, end: Bound<&K>) -> Self {
+        let start = match start {
+            Bound::Included(key) => map.count_keys_below(key),
+            Bound::Excluded(key) => map.count_keys_up_to(key),
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(key) => map.count_keys_up_to(key),
+            Bound::Excluded(key) => map.count_keys_below(key),
+            Bound::Unbounded => map.len(),
+        }
+        .max(start);
+        Self { map, start, end, pos: start + 1 }
+    }
+
+    /// Returns the entry the cursor points at without moving, or `None` on a ghost position.
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        if self.pos <= self.start || self.pos > self.end {
+            return None;
+        }
+        self.map.avl.nth_from_start(self.pos - 1).map(|Pair { key, val }| (key, val))
+    }
+
+    /// Moves one entry forward and returns the entry now pointed at, or `None` once past the end of the range.
+    pub fn move_next(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.pos <= self.end {
+            self.pos += 1;
+        }
+        self.peek()
+    }
+
+    /// Moves one entry back and returns the entry now pointed at, or `None` once before the start of the range.
+    pub fn move_prev(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.pos > self.start {
+            self.pos -= 1;
+        }
+        self.peek()
+    }
+}
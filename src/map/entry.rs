@@ -0,0 +1,98 @@
+use std::borrow::Borrow;
+
+use crate::Pair;
+
+use super::BTreeMap;
+
+/// A view into a single entry of a `BTreeMap` looked up by a borrowed key, created by `BTreeMap::entry_ref`.
+///
+/// The owned key is only created, with `ToOwned`, when a vacant entry gets a value inserted.
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, V> {
+    Occupied(OccupiedEntryRef<'a, K, V>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V>),
+}
+
+/// An entry whose key is present in the map
+pub struct OccupiedEntryRef<'a, K, V> {
+    pub(crate) entry: &'a mut Pair<K, V>,
+}
+
+/// An entry whose key is absent from the map
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, V> {
+    pub(crate) map: &'a mut BTreeMap<K, V>,
+    pub(crate) key: &'b Q,
+    /// Where the entry belongs, found by `BTreeMap::entry_ref`
+    pub(crate) rank: usize,
+}
+
+impl<'a, K, V> OccupiedEntryRef<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.entry.key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.entry.val
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.entry.val
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.entry.val
+    }
+
+    /// Replaces the value and returns the previous one
+    pub fn insert(&mut self, val: V) -> V {
+        std::mem::replace(&mut self.entry.val, val)
+    }
+}
+
+impl<'a, 'b, K, Q, V> VacantEntryRef<'a, 'b, K, Q, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord + ToOwned<Owned = K>,
+{
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Inserts `val` under an owned copy of the key and returns a reference to it. The key is not compared again.
+    pub fn insert(self, val: V) -> &'a mut V {
+        let avl = &mut self.map.avl;
+        avl.insert_nth(self.rank, Pair { key: self.key.to_owned(), val });
+        &mut avl.root.as_mut().and_then(|r| r.nth_mut(self.rank)).expect("the entry was just inserted at this rank").val
+    }
+}
+
+impl<'a, 'b, K, Q, V> EntryRef<'a, 'b, K, Q, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord + ToOwned<Owned = K>,
+{
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Applies `f` to the value if the entry is occupied
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
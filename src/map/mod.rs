@@ -1,21 +1,53 @@
-use std::fmt::Debug;
+use std::{borrow::Borrow, cmp::Ordering, fmt::Debug, iter::FusedIterator, ops::RangeBounds};
 
-use crate::{AVL, Pair};
+use crate::{node::{after_end, before_start}, AVL, Pair};
+
+mod cursor;
+pub use cursor::*;
+mod entry;
+pub use entry::*;
+
+/// One difference between two maps found by `BTreeMap::diff`, reading `self` as the old map and `other` as the new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapDiff<'a, K, V> {
+    /// The key is only in the new map
+    Added(&'a K, &'a V),
+    /// The key is only in the old map
+    Removed(&'a K, &'a V),
+    /// The key is in both maps with different values, old value first
+    Changed(&'a K, &'a V, &'a V),
+}
 
 pub struct BTreeMap<K, V> {
     pub(crate) avl: AVL<Pair<K, V>>,
 }
 
+impl<K, V> Default for BTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K, V> BTreeMap<K, V> {
     pub fn new() -> Self {
         Self { avl: AVL::new() }
     }
 
-    #[cfg(debug_assertions)]
+    /// Consumes the map and returns the tree of entries backing it, for running tree-level operations on them.
+    pub fn into_avl(self) -> AVL<Pair<K, V>> {
+        self.avl
+    }
+
+    /// Returns the tree of entries backing this map.
     pub fn avl(&self) -> &AVL<Pair<K, V>> {
         &self.avl
     }
 
+    /// Moves every entry out into a new map and leaves this one empty.
+    pub fn take_all(&mut self) -> BTreeMap<K, V> {
+        Self { avl: self.avl.take_all() }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.avl.is_empty()
     }
@@ -32,6 +64,13 @@ impl<K: Debug + Ord, V: Debug> Debug for BTreeMap<K, V> {
 }
 
 impl<K: Ord, V> BTreeMap<K, V> {
+    /// Wraps a tree of entries into a map. The tree must not hold two entries with equal keys,
+    /// which is checked in debug builds.
+    pub fn from_avl(avl: AVL<Pair<K, V>>) -> Self {
+        debug_assert!(avl.increasing().zip(avl.increasing().skip(1)).all(|(a, b)| a.key < b.key), "from_avl given duplicate keys");
+        Self { avl }
+    }
+
     pub fn contains_key(&self, key: &K) -> bool {
         self.avl.root.as_ref().map(|v| v.contains_by(|en| key.cmp(&en.key))).unwrap_or(false)
     }
@@ -45,7 +84,67 @@ impl<K: Ord, V> BTreeMap<K, V> {
     }
 
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        self.avl.root.as_mut().map(|v| v.get_mut_by(|en| key.cmp(&en.key))).unwrap_or(None).map(|v| &mut v.val)
+        self.avl.find_mut_with(|v| v.get_mut_by(|en| key.cmp(&en.key))).map(|v| &mut v.val)
+    }
+
+    /// Returns the entry for a borrowed form of the key, such as a `&str` for `String` keys.
+    ///
+    /// Unlike inserting with an owned key, a hit never builds an owned key; one is only made with `ToOwned` when a
+    /// vacant entry gets a value inserted.
+    pub fn entry_ref<'a, 'b, Q>(&'a mut self, key: &'b Q) -> EntryRef<'a, 'b, K, Q, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord + ToOwned<Owned = K>,
+    {
+        // The key is compared in one descent that only reads the tree and yields a rank. The entry is then reached
+        // through the subtree sizes, since a mutable search cannot hand its miss back to the vacant entry.
+        match self.avl.root.as_ref().map(|v| v.rank_by(|en| key.cmp(en.key.borrow()))).unwrap_or(Err(0)) {
+            Ok(rank) => {
+                let entry = self.avl.nth_mut(rank).expect("the rank was found in this tree");
+                EntryRef::Occupied(OccupiedEntryRef { entry })
+            }
+            Err(rank) => EntryRef::Vacant(VacantEntryRef { map: self, key, rank }),
+        }
+    }
+
+    /// Installs `new` as the value of `key` and returns the previous value, but only if `key` is already present.
+    /// On a miss the map is left unchanged and `None` is returned.
+    pub fn replace_value(&mut self, key: &K, new: V) -> Option<V> {
+        self.get_mut(key).map(|val| std::mem::replace(val, new))
+    }
+
+    /// Returns the value stored for `key`, or `default` when there is none. Nothing is inserted.
+    pub fn get_or<'a>(&'a self, key: &K, default: &'a V) -> &'a V {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Like `get_or`, but only calls `default` when `key` is missing.
+    pub fn get_or_else<'a, F: FnOnce() -> &'a V>(&'a self, key: &K, default: F) -> &'a V {
+        self.get(key).unwrap_or_else(default)
+    }
+
+    /// Returns a clone of the value stored for `key`, or `default` when there is none. Nothing is inserted.
+    pub fn get_cloned_or(&self, key: &K, default: V) -> V
+    where
+        V: Clone,
+    {
+        self.get(key).cloned().unwrap_or(default)
+    }
+
+    /// Looks up every key of `keys`, returning the results in the same order as the keys.
+    ///
+    /// The keys are sorted and matched against the entries in a single sweep, costing `O(n + q log(q))`
+    /// for `q` keys instead of `q` separate descents. Repeated keys are allowed.
+    pub fn get_many<'a>(&'a self, keys: &[K]) -> Vec<Option<&'a V>> {
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        let mut found = vec![None; keys.len()];
+        let mut entries = self.iter().peekable();
+        for i in order {
+            while entries.next_if(|en| en.key < keys[i]).is_some() {}
+            found[i] = entries.peek().filter(|en| en.key == keys[i]).map(|en| &en.val);
+        }
+        found
     }
 
     pub fn insert(&mut self, key: K, val: V) -> bool {
@@ -53,16 +152,149 @@ impl<K: Ord, V> BTreeMap<K, V> {
         self.avl.insert_distinct(entry)
     }
 
+    /// Inserts the entry only if `key` is not present yet, returning whether it did. On a collision the existing
+    /// value is kept, so the first write wins.
+    pub fn insert_if_absent(&mut self, key: K, val: V) -> bool {
+        self.avl.insert_if_absent(Pair { key, val })
+    }
+
+    /// Returns the value stored for `key`, first inserting `compute(&key)` if the key is absent.
+    ///
+    /// `compute` runs at most once per call and only when `key` is missing, which makes this a memoization primitive.
+    /// The key is compared only during a single descent, which also yields the rank its entry is then fetched by.
+    pub fn get_or_compute<F: FnOnce(&K) -> V>(&mut self, key: K, compute: F) -> &V {
+        let rank = match self.avl.root.as_ref().map(|r| r.rank_by(|en| key.cmp(&en.key))).unwrap_or(Err(0)) {
+            Ok(rank) => rank,
+            Err(rank) => {
+                let val = compute(&key);
+                self.avl.insert_nth(rank, Pair { key, val });
+                rank
+            }
+        };
+        &self.avl.nth_from_start(rank).expect("the entry sits at the rank just found").val
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    pub fn pop_first(&mut self) -> Option<Pair<K, V>> {
+        self.avl.pop_first()
+    }
+
+    /// Removes and returns the entry with the largest key.
+    pub fn pop_last(&mut self) -> Option<Pair<K, V>> {
+        self.avl.pop_last()
+    }
+
+    /// Inserts the entry, then evicts and returns the entry with the smallest key if the map grew past `max_len`.
+    ///
+    /// This keeps the map as a bounded sorted buffer holding the `max_len` largest keys seen.
+    pub fn insert_capped(&mut self, key: K, val: V, max_len: usize) -> Option<(K, V)> {
+        self.insert(key, val);
+        if self.len() > max_len {
+            self.pop_first().map(|en| (en.key, en.val))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of entries whose key is strictly less than `key`, in `O(log(n))`.
+    pub fn count_keys_below(&self, key: &K) -> usize {
+        self.avl.root.as_ref().map(|v| v.count_less_by(|en| key.cmp(&en.key))).unwrap_or(0)
+    }
+
+    /// Returns the number of entries whose key is less than or equal to `key`, in `O(log(n))`.
+    pub(crate) fn count_keys_up_to(&self, key: &K) -> usize {
+        self.avl.root.as_ref().map(|v| v.count_less_by(|en| if en.key <= *key { Ordering::Greater } else { Ordering::Less })).unwrap_or(0)
+    }
+
+    /// Returns the number of entries whose key is greater than or equal to `key`, in `O(log(n))`.
+    pub fn count_keys_at_or_above(&self, key: &K) -> usize {
+        self.len() - self.count_keys_below(key)
+    }
+
+    /// Returns a cursor over the entries whose key lies in `range`, pointing at the first of them.
+    pub fn range_cursor<R: RangeBounds<K>>(&self, range: R) -> RangeCursor<'_, K, V> {
+        RangeCursor::new(self, range.start_bound(), range.end_bound())
+    }
+
+    /// Applies `f` to every entry whose key lies in `range`, in increasing key order.
+    ///
+    /// Subtrees lying entirely outside of `range` are never visited, so this costs `O(log(n) + k)` for `k` updated entries.
+    pub fn update_range<R: RangeBounds<K>, F: FnMut(&K, &mut V)>(&mut self, range: R, mut f: F) {
+        let mut touched = false;
+        if let Some(root) = &mut self.avl.root {
+            root.for_each_mut_within(
+                &|en: &Pair<K, V>| before_start(&range, &en.key),
+                &|en: &Pair<K, V>| after_end(&range, &en.key),
+                &mut |en| {
+                    touched = true;
+                    f(&en.key, &mut en.val)
+                },
+            );
+        }
+        if touched {
+            self.avl.bump_generation();
+        }
+    }
+
+    /// Moves every entry of `other` into this map. For keys present in both, `combine` folds the incoming
+    /// value into the existing one instead of overwriting it.
+    pub fn merge_with<F: Fn(&mut V, V)>(&mut self, other: BTreeMap<K, V>, combine: F) {
+        for Pair { key, val } in other {
+            match self.get_mut(&key) {
+                Some(existing) => combine(existing, val),
+                None => {
+                    self.insert(key, val);
+                }
+            }
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Pair<K, V>> {
         self.avl.increasing()
     }
 
-    pub fn keys(&self) -> impl Iterator<Item = &K> {
-        self.avl.increasing().map(|v| &v.key)
+    /// Returns `(key, value)` pairs in increasing key order with an exact `size_hint`.
+    ///
+    /// This is the traversal that `keys` and `values` are projected from.
+    pub fn iter_kv(&self) -> impl ExactSizeIterator<Item = (&K, &V)> + FusedIterator {
+        self.avl.iter_from_rank(0).map(|en| (&en.key, &en.val))
+    }
+
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + FusedIterator {
+        self.iter_kv().map(|(k, _)| k)
     }
 
-    pub fn values(&self) -> impl Iterator<Item = &V> {
-        self.avl.increasing().map(|v| &v.val)
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &V> + FusedIterator {
+        self.iter_kv().map(|(_, v)| v)
+    }
+
+    /// Returns the keys and the values as two index-aligned vectors in increasing key order, so that
+    /// `(keys[i], values[i])` is the `i`-th entry.
+    pub fn to_sorted_vecs(&self) -> (Vec<&K>, Vec<&V>) {
+        let mut keys = Vec::with_capacity(self.len());
+        let mut values = Vec::with_capacity(self.len());
+        for en in self.increasing() {
+            keys.push(&en.key);
+            values.push(&en.val);
+        }
+        (keys, values)
+    }
+
+    /// Consumes the map and bulk-loads its keys, which already come out sorted, into a balanced tree in `O(n)`.
+    pub fn into_key_set(self) -> AVL<K> {
+        let mut keys = AVL::new();
+        keys.replace_sorted(self.into_keys().collect());
+        keys
+    }
+
+    /// Consumes the map and builds a balanced tree of its values, keeping repeated values.
+    ///
+    /// The values are not in order, so they are sorted before the tree is bulk-loaded.
+    pub fn into_value_multiset(self) -> AVL<V>
+    where
+        V: Ord,
+    {
+        AVL::from_unsorted_vec(self.into_values().collect())
     }
 
     pub fn into_keys(self) -> impl Iterator<Item = K> {
@@ -73,6 +305,42 @@ impl<K: Ord, V> BTreeMap<K, V> {
         self.avl.into_increasing().map(|v| v.val)
     }
 
+    /// Consumes the map and yields its entries as `(key, value)` pairs in decreasing key order.
+    pub fn into_decreasing(self) -> impl Iterator<Item = (K, V)> {
+        self.avl.into_decreasing().map(|en| (en.key, en.val))
+    }
+
+    /// Inner merge-join on the keys present in both maps, yielded in increasing key order in `O(n + m)`.
+    pub fn join<'a, W>(&'a self, other: &'a BTreeMap<K, W>) -> impl Iterator<Item = (&'a K, &'a V, &'a W)> {
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        std::iter::from_fn(move || loop {
+            match left.peek()?.key.cmp(&right.peek()?.key) {
+                Ordering::Less => {
+                    left.next();
+                }
+                Ordering::Greater => {
+                    right.next();
+                }
+                Ordering::Equal => {
+                    let (l, r) = (left.next()?, right.next()?);
+                    return Some((&l.key, &l.val, &r.val));
+                }
+            }
+        })
+    }
+
+    /// Left outer merge-join: every entry of `self` in increasing key order, paired with the value `other` holds
+    /// for the same key, if any. Runs in `O(n + m)`.
+    pub fn left_join<'a, W>(&'a self, other: &'a BTreeMap<K, W>) -> impl Iterator<Item = (&'a K, &'a V, Option<&'a W>)> {
+        let mut right = other.iter().peekable();
+        self.iter().map(move |l| {
+            while right.next_if(|r| r.key < l.key).is_some() {}
+            let matched = right.next_if(|r| r.key == l.key).map(|r| &r.val);
+            (&l.key, &l.val, matched)
+        })
+    }
+
     pub fn increasing(&self) -> impl Iterator<Item = &Pair<K, V>> {
         self.avl.increasing()
     }
@@ -82,6 +350,33 @@ impl<K: Ord, V> BTreeMap<K, V> {
     }
 }
 
+impl<K: Ord, V: PartialEq> BTreeMap<K, V> {
+    /// Reports the entries added, removed and changed going from `self` to `other`, in increasing key order.
+    ///
+    /// Both maps are walked once by key in `O(n + m)`; keys holding equal values in both maps are skipped.
+    pub fn diff<'a>(&'a self, other: &'a BTreeMap<K, V>) -> impl Iterator<Item = MapDiff<'a, K, V>> {
+        let mut old = self.iter().peekable();
+        let mut new = other.iter().peekable();
+        std::iter::from_fn(move || loop {
+            let order = match (old.peek(), new.peek()) {
+                (Some(o), Some(n)) => o.key.cmp(&n.key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+            match order {
+                Ordering::Less => return old.next().map(|o| MapDiff::Removed(&o.key, &o.val)),
+                Ordering::Greater => return new.next().map(|n| MapDiff::Added(&n.key, &n.val)),
+                Ordering::Equal => {
+                    let (o, n) = (old.next()?, new.next()?);
+                    if o.val != n.val {
+                        return Some(MapDiff::Changed(&o.key, &o.val, &n.val));
+                    }
+                }
+            }
+        })
+    }
+}
 
 impl<K: Ord, V> IntoIterator for BTreeMap<K, V> {
     type IntoIter = crate::iters::IntoIncreasing<Pair<K, V>>;
@@ -89,4 +384,7 @@ impl<K: Ord, V> IntoIterator for BTreeMap<K, V> {
     fn into_iter(self) -> Self::IntoIter {
         crate::iters::IntoIncreasing::new(self.avl.root)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,507 @@
+use super::*;
+
+/// Counts the allocations made by the current thread, so that tests running in parallel do not disturb each other
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|n| n.get())
+}
+
+#[test]
+fn count_keys_below_and_at_or_above() {
+    let mut map = BTreeMap::new();
+    for k in (0..60).map(|x| x * 3) {
+        map.insert(k, k);
+    }
+    for q in -1..200 {
+        let below = map.keys().filter(|&&k| k < q).count();
+        assert_eq!(map.count_keys_below(&q), below);
+        assert_eq!(map.count_keys_at_or_above(&q), map.len() - below);
+    }
+    assert_eq!(BTreeMap::<i32, i32>::new().count_keys_below(&1), 0);
+}
+
+#[test]
+fn update_range_touches_only_the_range() {
+    let mut map = BTreeMap::new();
+    for k in 0..50 {
+        map.insert(k, 0);
+    }
+    let mut touched = 0;
+    map.update_range(10..20, |_, v| {
+        *v += 1;
+        touched += 1;
+    });
+    assert_eq!(touched, 10);
+    for (k, v) in map.keys().zip(map.values()) {
+        assert_eq!(*v, if (10..20).contains(k) { 1 } else { 0 });
+    }
+
+    let mut seen = vec![];
+    map.update_range(..=3, |k, _| seen.push(*k));
+    assert_eq!(seen, vec![0, 1, 2, 3]);
+    let mut seen = vec![];
+    map.update_range(
+        (std::ops::Bound::Excluded(47), std::ops::Bound::Unbounded),
+        |k, _| seen.push(*k),
+    );
+    assert_eq!(seen, vec![48, 49]);
+}
+
+#[test]
+fn join_keeps_common_keys() {
+    let mut a = BTreeMap::new();
+    a.insert(1, 'a');
+    a.insert(2, 'b');
+    a.insert(3, 'c');
+    let mut b = BTreeMap::new();
+    b.insert(2, "x");
+    b.insert(3, "y");
+    b.insert(4, "z");
+    let joined: Vec<_> = a.join(&b).collect();
+    assert_eq!(joined, vec![(&2, &'b', &"x"), (&3, &'c', &"y")]);
+}
+
+#[test]
+fn left_join_yields_none_for_missing_keys() {
+    let mut a = BTreeMap::new();
+    a.insert(1, 'a');
+    a.insert(2, 'b');
+    a.insert(3, 'c');
+    a.insert(9, 'd');
+    let mut b = BTreeMap::new();
+    b.insert(0, "w");
+    b.insert(2, "x");
+    b.insert(3, "y");
+    b.insert(4, "z");
+    let joined: Vec<_> = a.left_join(&b).collect();
+    assert_eq!(
+        joined,
+        vec![
+            (&1, &'a', None),
+            (&2, &'b', Some(&"x")),
+            (&3, &'c', Some(&"y")),
+            (&9, &'d', None)
+        ]
+    );
+}
+
+#[test]
+fn avl_round_trip_preserves_entries() {
+    let mut map = BTreeMap::new();
+    for k in 0..20 {
+        map.insert(k, k * 2);
+    }
+    let mut avl = map.into_avl();
+    avl.insert_distinct(Pair::new(100, 1));
+    let map = BTreeMap::from_avl(avl);
+    assert_eq!(map.len(), 21);
+    assert_eq!(map.get(&100), Some(&1));
+    assert!((0..20).all(|k| map.get(&k) == Some(&(k * 2))));
+}
+
+#[test]
+fn avl_exposes_the_backing_tree() {
+    let mut map = BTreeMap::new();
+    for k in [2, 1, 3] {
+        map.insert(k, k * 10);
+    }
+    let levels: Vec<Vec<Option<i32>>> = map
+        .avl()
+        .levels()
+        .map(|l| l.map(|p| p.map(|p| p.key)).collect())
+        .collect();
+    assert_eq!(levels, vec![vec![Some(2)], vec![Some(1), Some(3)]]);
+}
+
+#[test]
+fn get_many_matches_get_in_input_order() {
+    let mut map = BTreeMap::new();
+    for k in 0..100 {
+        map.insert(k * 2, k);
+    }
+    let keys = vec![5, 4, 198, 4, -1, 200, 0, 66];
+    let want: Vec<_> = keys.iter().map(|k| map.get(k)).collect();
+    assert_eq!(map.get_many(&keys), want);
+    assert_eq!(BTreeMap::<i32, i32>::new().get_many(&[1]), vec![None]);
+}
+
+#[test]
+fn merge_with_sums_collisions() {
+    let mut a = BTreeMap::new();
+    a.insert("x", 1);
+    a.insert("y", 2);
+    let mut b = BTreeMap::new();
+    b.insert("y", 10);
+    b.insert("z", 5);
+    a.merge_with(b, |e, v| *e += v);
+    let entries: Vec<_> = a.iter().map(|p| (p.key, p.val)).collect();
+    assert_eq!(entries, vec![("x", 1), ("y", 12), ("z", 5)]);
+}
+
+#[test]
+fn to_sorted_vecs_is_index_aligned() {
+    let mut map = BTreeMap::new();
+    for k in [5, 2, 9, 1] {
+        map.insert(k, k * 10);
+    }
+    let (keys, vals) = map.to_sorted_vecs();
+    assert_eq!(keys.len(), vals.len());
+    for (i, en) in map.iter().enumerate() {
+        assert_eq!((keys[i], vals[i]), (&en.key, &en.val));
+    }
+    assert_eq!(keys, vec![&1, &2, &5, &9]);
+    assert!(BTreeMap::<i32, i32>::new().to_sorted_vecs().0.is_empty());
+}
+
+#[test]
+fn get_or_compute_runs_once_per_key() {
+    let mut map: BTreeMap<u64, String> = BTreeMap::new();
+    let mut calls = 0;
+    for k in [3, 1, 3, 7, 1, 3, 0] {
+        let v = map.get_or_compute(k, |k| {
+            calls += 1;
+            format!("v{k}")
+        });
+        assert_eq!(v, &format!("v{k}"));
+    }
+    assert_eq!(calls, 4);
+    assert_eq!(map.len(), 4);
+}
+
+thread_local! {
+    static KEY_COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// A key that counts how often it is compared
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct CountedKey(u64);
+
+impl PartialOrd for CountedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CountedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        KEY_COMPARISONS.with(|c| c.set(c.get() + 1));
+        self.0.cmp(&other.0)
+    }
+}
+
+fn key_comparisons(f: impl FnOnce()) -> usize {
+    KEY_COMPARISONS.with(|c| c.set(0));
+    f();
+    KEY_COMPARISONS.with(|c| c.get())
+}
+
+#[test]
+fn get_or_compute_compares_along_one_path() {
+    let mut map = BTreeMap::new();
+    for k in 0..1023 {
+        map.insert(CountedKey(k * 2), k);
+    }
+    let height = map.avl().height();
+    let hit = key_comparisons(|| assert_eq!(map.get_or_compute(CountedKey(500), |_| 0), &250));
+    assert!(
+        hit <= height,
+        "{hit} comparisons for a hit at height {height}"
+    );
+    let miss = key_comparisons(|| assert_eq!(map.get_or_compute(CountedKey(501), |k| k.0), &501));
+    assert!(
+        miss <= height,
+        "{miss} comparisons for a miss at height {height}"
+    );
+    assert_eq!(map.len(), 1024);
+    assert!(map.avl().is_balanced());
+}
+
+#[test]
+fn insert_capped_evicts_the_minimum() {
+    let mut map = BTreeMap::new();
+    assert_eq!(map.insert_capped(5, 'a', 3), None);
+    assert_eq!(map.insert_capped(2, 'b', 3), None);
+    assert_eq!(map.insert_capped(9, 'c', 3), None);
+    assert_eq!(map.insert_capped(7, 'd', 3), Some((2, 'b')));
+    assert_eq!(map.insert_capped(1, 'e', 3), Some((1, 'e')));
+    assert_eq!(map.insert_capped(9, 'f', 3), None);
+    assert!(map.keys().copied().eq([5, 7, 9]));
+    assert_eq!(map.get(&9), Some(&'f'));
+}
+
+#[test]
+fn diff_reports_changed_values() {
+    let mut old = BTreeMap::new();
+    let mut new = BTreeMap::new();
+    for (k, v) in [(1, "x"), (2, "y"), (3, "z")] {
+        old.insert(k, v);
+    }
+    for (k, v) in [(2, "y"), (3, "Z"), (4, "w")] {
+        new.insert(k, v);
+    }
+    let diff: Vec<_> = old.diff(&new).collect();
+    assert_eq!(
+        diff,
+        vec![
+            MapDiff::Removed(&1, &"x"),
+            MapDiff::Changed(&3, &"z", &"Z"),
+            MapDiff::Added(&4, &"w")
+        ]
+    );
+    assert_eq!(old.diff(&old).count(), 0);
+}
+
+#[test]
+fn insert_if_absent_keeps_the_first_value() {
+    let mut map = BTreeMap::new();
+    assert!(map.insert_if_absent(3, "first"));
+    assert!(!map.insert_if_absent(3, "second"));
+    assert_eq!(map.get(&3), Some(&"first"));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn iter_kv_is_ordered_and_exact() {
+    let mut map = BTreeMap::new();
+    for k in [4, 1, 8, 2] {
+        map.insert(k, k * 3);
+    }
+    let mut it = map.iter_kv();
+    assert_eq!(it.size_hint(), (4, Some(4)));
+    assert_eq!(it.next(), Some((&1, &3)));
+    assert_eq!(it.len(), 3);
+    assert!(it.eq([(&2, &6), (&4, &12), (&8, &24)]));
+
+    let mut keys = map.keys();
+    for _ in 0..4 {
+        keys.next();
+    }
+    assert_eq!(keys.next(), None);
+    assert_eq!(keys.next(), None);
+    assert_eq!(map.values().len(), 4);
+    assert!(map.values().copied().eq([3, 6, 12, 24]));
+}
+
+#[test]
+fn get_or_hit_and_miss() {
+    let mut map = BTreeMap::new();
+    map.insert("port", 80);
+    let default = 8080;
+    assert_eq!(map.get_or(&"port", &default), &80);
+    assert_eq!(map.get_or(&"tls", &default), &8080);
+    assert_eq!(map.get_or_else(&"tls", || &default), &8080);
+    assert_eq!(map.get_or_else(&"port", || unreachable!()), &80);
+    assert_eq!(map.get_cloned_or(&"port", 1), 80);
+    assert_eq!(map.get_cloned_or(&"x", 1), 1);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn range_cursor_steps_both_ways() {
+    let mut map = BTreeMap::new();
+    for k in (0..40).step_by(3) {
+        map.insert(k, k * 2);
+    }
+    let mut cur = map.range_cursor(10..20);
+    assert_eq!(cur.peek(), Some((&12, &24)));
+    assert_eq!(cur.peek(), Some((&12, &24)));
+    assert_eq!(cur.move_next(), Some((&15, &30)));
+    assert_eq!(cur.move_next(), Some((&18, &36)));
+    assert_eq!(cur.move_next(), None);
+    assert_eq!(cur.move_next(), None);
+    assert_eq!(cur.move_prev(), Some((&18, &36)));
+    assert_eq!(cur.move_prev(), Some((&15, &30)));
+    assert_eq!(cur.move_prev(), Some((&12, &24)));
+    assert_eq!(cur.move_prev(), None);
+    assert_eq!(cur.move_prev(), None);
+    assert_eq!(cur.move_next(), Some((&12, &24)));
+}
+
+#[test]
+fn range_cursor_bounds() {
+    let mut map = BTreeMap::new();
+    for k in (0..40).step_by(3) {
+        map.insert(k, k * 2);
+    }
+    assert_eq!(map.range_cursor(12..=12).peek(), Some((&12, &24)));
+    let mut cur = map.range_cursor((std::ops::Bound::Excluded(12), std::ops::Bound::Included(18)));
+    assert_eq!(cur.peek(), Some((&15, &30)));
+    assert_eq!(cur.move_next(), Some((&18, &36)));
+    let mut cur = map.range_cursor(13..14);
+    assert_eq!(cur.peek(), None);
+    assert_eq!(cur.move_next(), None);
+    assert_eq!(cur.move_prev(), None);
+    assert_eq!(map.range_cursor(..).peek(), Some((&0, &0)));
+    #[allow(clippy::reversed_empty_ranges)]
+    let cur = map.range_cursor(20..10);
+    assert_eq!(cur.peek(), None);
+}
+
+#[test]
+fn replace_value_never_inserts() {
+    let mut map = BTreeMap::new();
+    map.insert(1, "a");
+    assert_eq!(map.replace_value(&1, "b"), Some("a"));
+    assert_eq!(map.get(&1), Some(&"b"));
+    assert_eq!(map.replace_value(&2, "c"), None);
+    assert_eq!(map.len(), 1);
+    assert!(!map.contains_key(&2));
+}
+
+#[test]
+fn entry_ref_hit_does_not_allocate() {
+    let mut map: BTreeMap<String, u32> = BTreeMap::new();
+    *map.entry_ref("apple").or_insert(0) += 1;
+    *map.entry_ref("pear").or_default() += 5;
+
+    let before = allocations();
+    *map.entry_ref("apple").or_insert(0) += 1;
+    map.entry_ref("pear").and_modify(|v| *v *= 2).or_insert(0);
+    assert_eq!(allocations(), before);
+
+    assert_eq!(map.get(&"apple".to_string()), Some(&2));
+    assert_eq!(map.get(&"pear".to_string()), Some(&10));
+}
+
+#[test]
+fn entry_ref_occupied_and_vacant() {
+    let mut map: BTreeMap<String, u32> = BTreeMap::new();
+    match map.entry_ref("kiwi") {
+        EntryRef::Vacant(v) => {
+            assert_eq!(v.key(), "kiwi");
+            *v.insert(3) += 1;
+        }
+        EntryRef::Occupied(_) => panic!("the map is empty"),
+    }
+    match map.entry_ref("kiwi") {
+        EntryRef::Occupied(mut o) => {
+            assert_eq!(o.key(), "kiwi");
+            assert_eq!(o.insert(9), 4);
+            assert_eq!(*o.get(), 9);
+        }
+        EntryRef::Vacant(_) => panic!("kiwi was inserted"),
+    }
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn into_key_set_and_value_multiset() {
+    let mut map = BTreeMap::new();
+    for k in 0..1000u32 {
+        map.insert((k * 7919) % 1000, k % 10);
+    }
+    let values = BTreeMap::from_avl(map.avl().clone());
+    let keys = map.into_key_set();
+    assert!(keys.increasing().copied().eq(0..1000));
+    assert!(keys.is_balanced());
+    let values = values.into_value_multiset();
+    assert_eq!(values.len(), 1000);
+    assert_eq!(values.distinct_len(), 10);
+    assert!(values.verify_ordering());
+}
+
+#[test]
+fn into_decreasing_drains_highest_first() {
+    let mut map = BTreeMap::new();
+    for k in [5, 1, 9, 3, 7] {
+        map.insert(k, k * 2);
+    }
+    let drained: Vec<(i32, i32)> = map.into_decreasing().collect();
+    assert_eq!(drained, vec![(9, 18), (7, 14), (5, 10), (3, 6), (1, 2)]);
+}
+
+#[test]
+fn take_all_empties_the_map() {
+    let mut map = BTreeMap::new();
+    map.insert(1, 'a');
+    map.insert(2, 'b');
+    let taken = map.take_all();
+    assert!(map.is_empty());
+    assert_eq!(taken.len(), 2);
+    assert_eq!(taken.get(&2), Some(&'b'));
+}
+
+#[test]
+fn value_writes_bump_the_generation() {
+    let mut map = BTreeMap::new();
+    map.insert(1, "a".to_string());
+    map.insert(2, "b".to_string());
+    let mut last = map.avl().generation();
+    let mut bumped = |map: &BTreeMap<i32, String>| {
+        let now = map.avl().generation();
+        let moved = now != last;
+        last = now;
+        moved
+    };
+    map.get_mut(&1).unwrap().push('!');
+    assert!(bumped(&map));
+    map.replace_value(&2, "c".to_string());
+    assert!(bumped(&map));
+    map.update_range(.., |_, v| v.push('?'));
+    assert!(bumped(&map));
+    let mut keyed: BTreeMap<String, i32> = BTreeMap::new();
+    keyed.insert("k".to_string(), 1);
+    let before = keyed.avl().generation();
+    *keyed.entry_ref("k").or_insert(0) += 1;
+    assert_ne!(keyed.avl().generation(), before);
+    let _ = (map.get(&1), map.contains_key(&2));
+    assert!(!bumped(&map));
+}
+
+#[test]
+fn missed_lookups_leave_the_generation_alone() {
+    let mut map = BTreeMap::new();
+    for k in 0..10 {
+        map.insert(k, k);
+    }
+    let before = map.avl().generation();
+    assert!(map.get_mut(&42).is_none());
+    assert_eq!(map.replace_value(&42, 0), None);
+    map.update_range(20..30, |_, v| *v += 1);
+    assert_eq!(map.avl().generation(), before);
+    assert_eq!(map.replace_value(&3, 30), Some(3));
+    assert_ne!(map.avl().generation(), before);
+}
+
+#[test]
+fn entry_ref_compares_along_one_path() {
+    let mut map = BTreeMap::new();
+    for k in 0..1023 {
+        map.insert(CountedKey(k * 2), k);
+    }
+    let height = map.avl().height();
+    let hit = key_comparisons(|| *map.entry_ref(&CountedKey(500)).or_insert(0) += 1);
+    assert!(
+        hit <= height,
+        "{hit} comparisons for a hit at height {height}"
+    );
+    let miss = key_comparisons(|| assert_eq!(*map.entry_ref(&CountedKey(501)).or_insert(7), 7));
+    assert!(
+        miss <= height,
+        "{miss} comparisons for a vacant insert at height {height}"
+    );
+    assert_eq!(map.get(&CountedKey(500)), Some(&251));
+    assert_eq!(map.get(&CountedKey(501)), Some(&7));
+    assert_eq!(map.len(), 1024);
+    assert!(map.avl().is_balanced());
+    assert!(map.keys().zip(map.keys().skip(1)).all(|(a, b)| a.0 < b.0));
+}
@@ -0,0 +1,105 @@
+use std::fmt::Debug;
+
+use crate::{AVL, Pair};
+
+/// A multiset that stores each distinct value once, alongside the number of times it was inserted.
+///
+/// Unlike building a multiset out of `AVL::insert`, repeated values cost no extra nodes.
+pub struct AVLMultiset<T> {
+    pub(crate) avl: AVL<Pair<T, usize>>,
+    total: usize,
+}
+
+impl<T> Default for AVLMultiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> AVLMultiset<T> {
+    pub fn new() -> Self {
+        Self { avl: AVL::new(), total: 0 }
+    }
+
+    /// Returns the number of distinct values
+    pub fn len(&self) -> usize {
+        self.avl.len()
+    }
+
+    /// Returns the number of values counting every copy
+    pub fn total_len(&self) -> usize {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.avl.is_empty()
+    }
+
+    /// Returns the distinct values in increasing order together with their counts
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.avl.increasing().map(|en| (&en.key, en.val))
+    }
+}
+
+impl<T: Debug + Ord> Debug for AVLMultiset<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> AVLMultiset<T> {
+    /// Adds one copy of `val`
+    pub fn insert(&mut self, val: T) {
+        self.insert_n(val, 1)
+    }
+
+    /// Adds `count` copies of `val` at once, touching a single entry. Adding zero copies changes nothing.
+    pub fn insert_n(&mut self, val: T, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let existing = self.avl.find_mut_with(|r| r.get_mut_by(|en| val.cmp(&en.key)));
+        if let Some(en) = existing {
+            en.val += count;
+        } else {
+            self.avl.insert_distinct(Pair { key: val, val: count });
+        }
+        self.total += count;
+    }
+
+    /// Removes one copy of `val`, dropping the value entirely once its count reaches zero.
+    /// Returns whether a copy was present.
+    pub fn remove_one(&mut self, val: &T) -> bool {
+        let Some(en) = self.avl.find_mut_with(|r| r.get_mut_by(|en| val.cmp(&en.key))) else {
+            return false;
+        };
+        en.val -= 1;
+        if en.val == 0 {
+            self.avl.remove_by(|en| val.cmp(&en.key));
+        }
+        self.total -= 1;
+        true
+    }
+
+    /// Returns how many copies of `val` are stored
+    pub fn count(&self, val: &T) -> usize {
+        self.avl.root.as_ref().and_then(|r| r.get_by(|en| val.cmp(&en.key))).map(|en| en.val).unwrap_or(0)
+    }
+
+    pub fn contains(&self, val: &T) -> bool {
+        self.count(val) > 0
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AVLMultiset<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for val in iter {
+            set.insert(val)
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,45 @@
+use super::*;
+
+#[test]
+fn repeated_inserts_share_one_node() {
+    let mut set = AVLMultiset::new();
+    for _ in 0..1_000_000 {
+        set.insert(7u8);
+    }
+    assert_eq!(set.avl.len(), 1);
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.count(&7), 1_000_000);
+    assert_eq!(set.total_len(), 1_000_000);
+
+    set.insert(3);
+    assert!(set.remove_one(&3));
+    assert!(!set.remove_one(&3));
+    assert_eq!(set.count(&3), 0);
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn iterates_values_with_counts() {
+    let set: AVLMultiset<i32> = [1, 2, 2, 3, 3, 3].into_iter().collect();
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        vec![(&1, 1), (&2, 2), (&3, 3)]
+    );
+    assert_eq!(format!("{set:?}"), "{1: 1, 2: 2, 3: 3}");
+}
+
+#[test]
+fn insert_n_bumps_one_node() {
+    let mut set = AVLMultiset::new();
+    set.insert_n(5, 1000);
+    assert_eq!(set.count(&5), 1000);
+    assert_eq!(set.avl.len(), 1);
+    assert_eq!(set.total_len(), 1000);
+    set.insert_n(5, 2);
+    set.insert(5);
+    set.insert_n(7, 0);
+    assert_eq!(set.count(&5), 1003);
+    assert!(!set.contains(&7));
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.total_len(), 1003);
+}
@@ -1,172 +1,373 @@
-use std::{fmt::Debug, cmp::Ordering};
+use std::{fmt::Debug, cmp::Ordering, ops::{Bound, ControlFlow, RangeBounds}};
+
+/// Whether `key` sorts before the start of `range`
+pub(crate) fn before_start<K: Ord>(range: &impl RangeBounds<K>, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+/// Whether `key` sorts after the end of `range`
+pub(crate) fn after_end<K: Ord>(range: &impl RangeBounds<K>, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(end) => key > end,
+        Bound::Excluded(end) => key >= end,
+        Bound::Unbounded => false,
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct Node<T> {
     pub(crate) height: i32,
+    pub(crate) size: usize,
     pub(crate) val: T,
     pub(crate) left: Option<Box<Node<T>>>,
     pub(crate) right: Option<Box<Node<T>>>,
 }
 
+/// The balance factor, rotations and rebalancing shared by the AVL trees whose nodes are uniquely owned boxes.
+///
+/// An implementor only exposes its height and children and says, in `update`, how to recompute what it caches about
+/// its subtree, such as the size of a `Node` or the greatest end point of an interval tree node. Every rotation calls
+/// `update` on the two nodes it moves, lower one first, so those caches stay right through the rebalancing.
+pub(crate) trait AvlNode: Sized {
+    fn height(&self) -> i32;
+    fn left(&self) -> &Option<Box<Self>>;
+    fn right(&self) -> &Option<Box<Self>>;
+    fn left_mut(&mut self) -> &mut Option<Box<Self>>;
+    fn right_mut(&mut self) -> &mut Option<Box<Self>>;
+
+    /// Recomputes the cached height and any other summary of the subtree from the children of this node
+    fn update(&mut self);
+
+    /// # Balance Factor
+    ///
+    /// This function computes and returns the balance factor of the currrent node
+    #[inline]
+    fn bf(&self) -> i32 {
+        self.left().as_ref().map(|l| l.height()).unwrap_or(0)
+            - self.right().as_ref().map(|r| r.height()).unwrap_or(0)
+    }
+
+    #[inline]
+    fn balance(self: &mut Box<Self>, rotations: &mut u64) {
+        let bf = self.bf();
+        if bf > 1 {
+            if let Some(left) = self.left_mut() {
+                if left.bf() < 0 {
+                    left.rotate_left(rotations);
+                }
+
+                self.rotate_right(rotations);
+            }
+        } else if bf < -1 {
+            if let Some(right) = self.right_mut() {
+                if right.bf() > 0 {
+                    right.rotate_right(rotations);
+                }
+                self.rotate_left(rotations);
+            }
+        }
+    }
+
+    #[inline]
+    fn rotate_left(self: &mut Box<Self>, rotations: &mut u64) {
+        if let Some(mut new_head) = self.right_mut().take() {
+            *rotations += 1;
+            let head_left = new_head.left_mut().take();
+            let mut old_head = std::mem::replace(self, new_head);
+            *old_head.right_mut() = head_left;
+            old_head.update();
+            *self.left_mut() = Some(old_head);
+            self.update();
+        }
+    }
+
+    #[inline]
+    fn rotate_right(self: &mut Box<Self>, rotations: &mut u64) {
+        if let Some(mut new_head) = self.left_mut().take() {
+            *rotations += 1;
+            let head_right = new_head.right_mut().take();
+            let mut old_head = std::mem::replace(self, new_head);
+            *old_head.left_mut() = head_right;
+            old_head.update();
+            *self.right_mut() = Some(old_head);
+            self.update();
+        }
+    }
+}
+
+impl<T> AvlNode for Node<T> {
+    #[inline]
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    #[inline]
+    fn left(&self) -> &Option<Box<Self>> {
+        &self.left
+    }
+
+    #[inline]
+    fn right(&self) -> &Option<Box<Self>> {
+        &self.right
+    }
+
+    #[inline]
+    fn left_mut(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.left
+    }
+
+    #[inline]
+    fn right_mut(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.right
+    }
+
+    #[inline]
+    fn update(&mut self) {
+        self.update_height()
+    }
+}
+
 impl<T: Ord> Node<T> {
     pub(crate) fn new(val: T) -> Self {
         Node {
             val,
             height: 1,
+            size: 1,
             left: None,
             right: None,
         }
     }
 
-    pub(crate) fn find_min(&self) -> &T {
-        if let Some(left) = &self.left {
-            left.find_min()
-        } else {
-            &self.val
-        }
+    /// Inserts `val`, replacing an equal element if there is one and returning the replaced element
+    pub(crate) fn insert_distinct(self: &mut Box<Self>, val: T, rotations: &mut u64) -> Option<T> {
+        self.insert_distinct_ranked(val, rotations).1
     }
 
-    pub(crate) fn find_max(&self) -> &T {
-        if let Some(right) = &self.right {
-            right.find_max()
-        } else {
-            &self.val
-        }
+    /// Same as `insert_distinct`, but also returns the rank at which `val` is now stored
+    pub(crate) fn insert_distinct_ranked(self: &mut Box<Self>, val: T, rotations: &mut u64) -> (usize, Option<T>) {
+        let res = match val.cmp(&self.val) {
+            Ordering::Less => if let Some(left) = &mut self.left {
+                left.insert_distinct_ranked(val, rotations)
+            } else {
+                self.left = Some(Box::new(Node::new(val)));
+                (0, None)
+            },
+            Ordering::Equal => (self.left_size(), Some(std::mem::replace(&mut self.val, val))),
+            Ordering::Greater => {
+                let offset = self.left_size() + 1;
+                let (rank, replaced) = if let Some(right) = &mut self.right {
+                    right.insert_distinct_ranked(val, rotations)
+                } else {
+                    self.right = Some(Box::new(Node::new(val)));
+                    (0, None)
+                };
+                (offset + rank, replaced)
+            }
+        };
+        self.update_height();
+        self.balance(rotations);
+        res
     }
 
-    pub(crate) fn insert_distinct(self: &mut Box<Self>, val: T) -> bool {
-        let res = match val.cmp(&self.val) {
+    /// Inserts `val` only if no equal element is present, returning whether it was inserted
+    pub(crate) fn insert_absent(self: &mut Box<Self>, val: T, rotations: &mut u64) -> bool {
+        let inserted = match val.cmp(&self.val) {
             Ordering::Less => if let Some(left) = &mut self.left {
-                left.insert_distinct(val)
+                left.insert_absent(val, rotations)
             } else {
-                self.left = Some(Box::new(Node {
-                    height: 1,
-                    val,
-                    left: None,
-                    right: None,
-                }));
+                self.left = Some(Box::new(Node::new(val)));
                 true
             },
-            Ordering::Equal => {
-                self.val = val;
-                false
-            },
+            Ordering::Equal => return false,
             Ordering::Greater => if let Some(right) = &mut self.right {
-                right.insert_distinct(val)
+                right.insert_absent(val, rotations)
             } else {
-                self.right = Some(Box::new(Node {
-                    height: 1,
-                    val,
-                    left: None,
-                    right: None,
-                }));
+                self.right = Some(Box::new(Node::new(val)));
                 true
             },
         };
-        self.update_height();
-        self.balance();
-        res
+        if inserted {
+            self.update_height();
+            self.balance(rotations);
+        }
+        inserted
     }
 
-    pub(crate) fn insert(self: &mut Box<Self>, val: T) {
-        if val < self.val {
+    /// Inserts `val` even if an equal element is already present.
+    ///
+    /// Equal elements always descend to the right, so a run of duplicates behaves exactly like an
+    /// increasing sequence of inserts and the rotations in `balance` keep the height logarithmic.
+    pub(crate) fn insert(self: &mut Box<Self>, val: T, rotations: &mut u64) {
+        if let Ordering::Less = val.cmp(&self.val) {
             if let Some(left) = &mut self.left {
-                left.insert(val)
+                left.insert(val, rotations)
             } else {
                 self.left = Some(Box::new(Node {
                     height: 1,
+                    size: 1,
                     val,
                     left: None,
                     right: None,
                 }));
             }
+        } else if let Some(right) = &mut self.right {
+            right.insert(val, rotations);
         } else {
-            if let Some(right) = &mut self.right {
-                right.insert(val);
-            } else {
-                self.right = Some(Box::new(Node {
-                    height: 1,
-                    val,
-                    left: None,
-                    right: None,
-                }));
+            self.right = Some(Box::new(Node {
+                height: 1,
+                size: 1,
+                val,
+                left: None,
+                right: None,
+            }));
+        }
+        self.update_height();
+        self.balance(rotations);
+    }
+
+    /// Same as `insert`, but also returns the number of elements that precede `val` once it has been inserted
+    pub(crate) fn insert_ranked(self: &mut Box<Self>, val: T, rotations: &mut u64) -> usize {
+        let rank = if let Ordering::Less = val.cmp(&self.val) {
+            match &mut self.left {
+                Some(left) => left.insert_ranked(val, rotations),
+                None => {
+                    self.left = Some(Box::new(Node::new(val)));
+                    0
+                }
+            }
+        } else {
+            let before = self.left_size() + 1;
+            before
+                + match &mut self.right {
+                    Some(right) => right.insert_ranked(val, rotations),
+                    None => {
+                        self.right = Some(Box::new(Node::new(val)));
+                        0
+                    }
+                }
+        };
+        self.update_height();
+        self.balance(rotations);
+        rank
+    }
+
+    /// Inserts `val` at in-order position `rank` by following the subtree sizes instead of comparing on the way down.
+    ///
+    /// `pred` and `succ` start out as the neighbours known to the caller and end up as the elements around the empty
+    /// slot that is reached; `val` is only placed there if `pred <= val < succ`, which is where `insert` would have put
+    /// it. Otherwise the subtree is left untouched and `val` is handed back.
+    pub(crate) fn insert_at_rank(self: &mut Box<Self>, rank: usize, val: T, pred: Option<&T>, succ: Option<&T>, rotations: &mut u64) -> Result<(), T> {
+        let left_size = self.left_size();
+        let Node { val: here, left, right, .. } = &mut **self;
+        let (slot, rank, pred, succ) = if rank <= left_size {
+            (left, rank, pred, Some(&*here))
+        } else {
+            (right, rank - left_size - 1, Some(&*here), succ)
+        };
+        match slot {
+            Some(child) => child.insert_at_rank(rank, val, pred, succ, rotations)?,
+            None => {
+                if pred.is_some_and(|p| p > &val) || succ.is_some_and(|s| s <= &val) {
+                    return Err(val);
+                }
+                *slot = Some(Box::new(Node::new(val)));
             }
         }
         self.update_height();
-        self.balance();
+        self.balance(rotations);
+        Ok(())
     }
 }
 
 impl<T> Node<T> {
-    /// # Balance Factor
-    ///
-    /// This function computes and returns the balance factor of the currrent node
-    #[inline]
-    pub(crate) fn bf(&self) -> i32 {
-        self.left.as_ref().map(|l| l.height).unwrap_or(0)
-            - self.right.as_ref().map(|r| r.height).unwrap_or(0)
+    pub(crate) fn find_min(&self) -> &T {
+        if let Some(left) = &self.left {
+            left.find_min()
+        } else {
+            &self.val
+        }
     }
-    
-    #[inline]
-    pub(crate) fn balance(self: &mut Box<Node<T>>) {
-        let bf = self.bf();
-        if bf > 1 {
-            if let Some(left) = &mut self.left {
-                if left.bf() < 0 {
-                    left.rotate_left();
-                }
 
-                self.rotate_right();
-            }
-        } else if bf < -1 {
-            if let Some(right) = &mut self.right {
-                if right.bf() > 0 {
-                    right.rotate_right();
-                }
-                self.rotate_left();
-            }
+    pub(crate) fn find_max(&self) -> &T {
+        if let Some(right) = &self.right {
+            right.find_max()
+        } else {
+            &self.val
+        }
+    }
+
+    /// Counts the nodes of this subtree by visiting them, without trusting the cached sizes
+    pub(crate) fn count_nodes(&self) -> usize {
+        1 + self.left.as_ref().map(|l| l.count_nodes()).unwrap_or(0) + self.right.as_ref().map(|r| r.count_nodes()).unwrap_or(0)
+    }
+
+    /// Returns the element at in-order position `index` within this subtree
+    pub(crate) fn nth(&self, index: usize) -> Option<&T> {
+        let left_size = self.left_size();
+        match index.cmp(&left_size) {
+            Ordering::Less => self.left.as_ref()?.nth(index),
+            Ordering::Equal => Some(&self.val),
+            Ordering::Greater => self.right.as_ref()?.nth(index - left_size - 1),
         }
     }
 
+    pub(crate) fn nth_mut(&mut self, index: usize) -> Option<&mut T> {
+        let left_size = self.left_size();
+        match index.cmp(&left_size) {
+            Ordering::Less => self.left.as_mut()?.nth_mut(index),
+            Ordering::Equal => Some(&mut self.val),
+            Ordering::Greater => self.right.as_mut()?.nth_mut(index - left_size - 1),
+        }
+    }
+
+    /// Recomputes the cached height and subtree size of this node from its children
     #[inline]
     pub(crate) fn update_height(&mut self) {
         self.height = 1 + i32::max(
             self.left.as_ref().map(|l| l.height).unwrap_or(0),
             self.right.as_ref().map(|r| r.height).unwrap_or(0),
         );
+        self.size = 1 + self.left_size() + self.right.as_ref().map(|r| r.size).unwrap_or(0);
     }
 
-    #[inline]
-    fn rotate_left(self: &mut Box<Node<T>>) {
-        if let Some(mut new_head) = self.right.take() {
-            let head_left = new_head.left.take();
-            let mut old_head = std::mem::replace(self, new_head);
-            old_head.right = head_left;
-            old_head.update_height();
-            self.left = Some(old_head);
-            self.update_height();
+    /// Recomputes the cached heights and sizes of the whole subtree bottom-up
+    pub(crate) fn fix_heights(&mut self) {
+        if let Some(left) = &mut self.left {
+            left.fix_heights();
+        }
+        if let Some(right) = &mut self.right {
+            right.fix_heights();
         }
+        self.update_height();
+    }
+
+    /// Returns the actual height of the subtree if every node in it satisfies the AVL balance condition and
+    /// caches its true height and size
+    pub(crate) fn checked_height(&self) -> Option<i32> {
+        let left = self.left.as_ref().map(|l| l.checked_height()).unwrap_or(Some(0))?;
+        let right = self.right.as_ref().map(|r| r.checked_height()).unwrap_or(Some(0))?;
+        let size = 1 + self.left_size() + self.right.as_ref().map(|r| r.size).unwrap_or(0);
+        let height = 1 + left.max(right);
+        ((left - right).abs() <= 1 && self.height == height && self.size == size).then_some(height)
     }
 
+    /// Number of nodes in the left subtree, which is also the in-order rank of this node within its own subtree
     #[inline]
-    fn rotate_right(self: &mut Box<Node<T>>) {
-        if let Some(mut new_head) = self.left.take() {
-            let head_right = new_head.right.take();
-            let mut old_head = std::mem::replace(self, new_head);
-            old_head.left = head_right;
-            old_head.update_height();
-            self.right = Some(old_head);
-            self.update_height();
-        }
+    pub(crate) fn left_size(&self) -> usize {
+        self.left.as_ref().map(|l| l.size).unwrap_or(0)
     }
 }
 
 impl<T: Ord> Node<T> {
-    pub(crate) fn remove_by(mut self: Box<Node<T>>, mut f: impl FnMut(&T) -> Ordering) -> (Option<T>, Option<Box<Node<T>>>) {
+    pub(crate) fn remove_by(mut self: Box<Node<T>>, mut f: impl FnMut(&T) -> Ordering, rotations: &mut u64) -> (Option<T>, Option<Box<Node<T>>>) {
         let (rem, mut res) = match f(&self.val) {
             Ordering::Less => {
                 if let Some(ln) = self.left.take() {
-                    let (r, ln) = ln.remove_by(f);
+                    let (r, ln) = ln.remove_by(f, rotations);
                     self.left = ln;
                     self.update_height();
                     (r, Some(self))
@@ -182,10 +383,11 @@ impl<T: Ord> Node<T> {
                             t_val = val;
                         }
                         let new_val = std::mem::replace(&mut t_val.val, self.val);
-                        let (d, right) = right.remove_by(f);
+                        let (d, right) = right.remove_by(f, rotations);
                         let left = Some(left);
                         let mut newnode = Box::new(Node {
                             height: 1,
+                            size: 1,
                             val: new_val,
                             left,
                             right,
@@ -199,7 +401,7 @@ impl<T: Ord> Node<T> {
             },
             Ordering::Greater => {
                 if let Some(rn) = self.right.take() {
-                    let (r, rn) = rn.remove_by(f);
+                    let (r, rn) = rn.remove_by(f, rotations);
                     self.right = rn;
                     self.update_height();
                     (r, Some(self))
@@ -209,10 +411,76 @@ impl<T: Ord> Node<T> {
             },
         };
 
-        res.as_mut().map(|v| v.balance());
+        if let Some(v) = res.as_mut() {
+            v.balance(rotations);
+        }
         (rem, res)
     }
-    pub(crate) fn delete(mut self: Box<Node<T>>, val: &T) -> (Option<T>, Option<Box<Node<T>>>) {
+
+    /// Detaches the element at in-order position `index` of this subtree, returning it together with what remains
+    pub(crate) fn remove_nth(mut self: Box<Node<T>>, index: usize, rotations: &mut u64) -> (Option<T>, Option<Box<Node<T>>>) {
+        let removed = match index.cmp(&self.left_size()) {
+            Ordering::Less => match self.left.take() {
+                Some(ln) => {
+                    let (r, ln) = ln.remove_nth(index, rotations);
+                    self.left = ln;
+                    r
+                }
+                None => None,
+            },
+            Ordering::Greater => match self.right.take() {
+                Some(rn) => {
+                    let (r, rn) = rn.remove_nth(index - self.left_size() - 1, rotations);
+                    self.right = rn;
+                    r
+                }
+                None => None,
+            },
+            Ordering::Equal => match (self.left.take(), self.right.take()) {
+                (left, None) => return (Some(self.val), left),
+                (None, right) => return (Some(self.val), right),
+                (left, Some(right)) => {
+                    let (successor, right) = right.pop_min(rotations);
+                    self.left = left;
+                    self.right = right;
+                    Some(std::mem::replace(&mut self.val, successor))
+                }
+            },
+        };
+        self.update_height();
+        self.balance(rotations);
+        (removed, Some(self))
+    }
+
+    /// Detaches the minimum element, returning it together with what remains of the subtree
+    pub(crate) fn pop_min(mut self: Box<Node<T>>, rotations: &mut u64) -> (T, Option<Box<Node<T>>>) {
+        match self.left.take() {
+            Some(ln) => {
+                let (min, ln) = ln.pop_min(rotations);
+                self.left = ln;
+                self.update_height();
+                self.balance(rotations);
+                (min, Some(self))
+            }
+            None => (self.val, self.right),
+        }
+    }
+
+    /// Detaches the maximum element, returning it together with what remains of the subtree
+    pub(crate) fn pop_max(mut self: Box<Node<T>>, rotations: &mut u64) -> (T, Option<Box<Node<T>>>) {
+        match self.right.take() {
+            Some(rn) => {
+                let (max, rn) = rn.pop_max(rotations);
+                self.right = rn;
+                self.update_height();
+                self.balance(rotations);
+                (max, Some(self))
+            }
+            None => (self.val, self.left),
+        }
+    }
+
+    pub(crate) fn delete(mut self: Box<Node<T>>, val: &T, rotations: &mut u64) -> (Option<T>, Option<Box<Node<T>>>) {
         let (con, mut rv) = if val == &self.val {
             match (self.left, self.right) {
                 (Some(left), Some(mut right)) => {
@@ -221,10 +489,11 @@ impl<T: Ord> Node<T> {
                         t_val = val;
                     }
                     let new_val = std::mem::replace(&mut t_val.val, self.val);
-                    let (d, right) = right.delete(&val);
+                    let (d, right) = right.delete(val, rotations);
                     let left = Some(left);
                     let mut newnode = Box::new(Node {
                         height: 1,
+                        size: 1,
                         val: new_val,
                         left,
                         right,
@@ -237,7 +506,7 @@ impl<T: Ord> Node<T> {
             }
         } else if val > &self.val {
             if let Some(rn) = self.right.take() {
-                let (r, rn) = rn.delete(val);
+                let (r, rn) = rn.delete(val, rotations);
                 self.right = rn;
                 self.update_height();
                 (r, Some(self))
@@ -246,7 +515,7 @@ impl<T: Ord> Node<T> {
             }
         } else {
             if let Some(ln) = self.left.take() {
-                let (r, ln) = ln.delete(val);
+                let (r, ln) = ln.delete(val, rotations);
                 self.left = ln;
                 self.update_height();
                 (r, Some(self))
@@ -254,7 +523,9 @@ impl<T: Ord> Node<T> {
                 (None, Some(self))
             }
         };
-        rv.as_mut().map(|v| v.balance());
+        if let Some(v) = rv.as_mut() {
+            v.balance(rotations);
+        }
         (con, rv)
     }
     pub(crate) fn nearest_to<'a, F>(&'a self, target: &'a T, by: &F) -> &'a T
@@ -281,6 +552,59 @@ impl<T: Ord> Node<T> {
         }
     }
 
+    pub(crate) fn nearest_with_distance<'a, D: Ord>(&'a self, target: &T, dist: &impl Fn(&T, &T) -> D) -> (&'a T, D) {
+        let d = dist(target, &self.val);
+        let next = match target.cmp(&self.val) {
+            Ordering::Less => self.left.as_ref(),
+            Ordering::Equal => None,
+            Ordering::Greater => self.right.as_ref(),
+        };
+        match next.map(|n| n.nearest_with_distance(target, dist)) {
+            Some((val, nd)) if nd < d => (val, nd),
+            _ => (&self.val, d),
+        }
+    }
+
+    /// Finds the greatest element `<= target` and the least element `>= target`, given the bounds found so far
+    pub(crate) fn bracket<'a>(&'a self, target: &T, floor: Option<&'a T>, ceil: Option<&'a T>) -> (Option<&'a T>, Option<&'a T>) {
+        match target.cmp(&self.val) {
+            Ordering::Less => match &self.left {
+                Some(left) => left.bracket(target, floor, Some(&self.val)),
+                None => (floor, Some(&self.val)),
+            },
+            Ordering::Equal => (Some(&self.val), Some(&self.val)),
+            Ordering::Greater => match &self.right {
+                Some(right) => right.bracket(target, Some(&self.val), ceil),
+                None => (Some(&self.val), ceil),
+            },
+        }
+    }
+
+    pub(crate) fn binary_search(&self, target: &T) -> Result<usize, usize> {
+        self.rank_by(|en| target.cmp(en))
+    }
+
+    /// Finds the element `f` reports as `Equal` in a single descent and returns its rank, or the rank at which such
+    /// an element would be inserted if there is none
+    pub(crate) fn rank_by(&self, mut f: impl FnMut(&T) -> Ordering) -> Result<usize, usize> {
+        let mut node = self;
+        let mut offset = 0;
+        loop {
+            let next = match f(&node.val) {
+                Ordering::Less => &node.left,
+                Ordering::Equal => return Ok(offset + node.left_size()),
+                Ordering::Greater => {
+                    offset += node.left_size() + 1;
+                    &node.right
+                }
+            };
+            match next {
+                Some(next) => node = next,
+                None => return Err(offset),
+            }
+        }
+    }
+
     pub(crate) fn contains(&self, target: &T) -> bool {
         match target.cmp(&self.val) {
             Ordering::Less => self.left.as_ref().map(|l| l.contains(target)).unwrap_or(false),
@@ -345,4 +669,161 @@ impl<T> Node<T> {
             Ordering::Greater => self.right.as_mut().map(|r| r.get_mut_by(f)).unwrap_or(None),
         }
     }
+
+    /// Appends `val` as the new rightmost element without comparing it against anything on the way down
+    pub(crate) fn push_back(self: &mut Box<Self>, val: T, rotations: &mut u64) {
+        if let Some(right) = &mut self.right {
+            let height = right.height;
+            right.push_back(val, rotations);
+            if right.height == height {
+                // the spine below kept its height, so nothing above it can have become unbalanced
+                self.size += 1;
+                return;
+            }
+        } else {
+            self.right = Some(Box::new(Node {
+                height: 1,
+                size: 1,
+                val,
+                left: None,
+                right: None,
+            }));
+        }
+        self.update_height();
+        self.balance(rotations);
+    }
+
+    /// Inserts `val` at in-order position `rank` of this subtree, following only the subtree sizes. The caller
+    /// guarantees that this is where `val` belongs, since it is never compared with anything.
+    pub(crate) fn insert_nth(self: &mut Box<Self>, rank: usize, val: T, rotations: &mut u64) {
+        let left_size = self.left_size();
+        let (slot, rank) = if rank <= left_size { (&mut self.left, rank) } else { (&mut self.right, rank - left_size - 1) };
+        match slot {
+            Some(child) => child.insert_nth(rank, val, rotations),
+            None => {
+                *slot = Some(Box::new(Node {
+                    height: 1,
+                    size: 1,
+                    val,
+                    left: None,
+                    right: None,
+                }))
+            }
+        }
+        self.update_height();
+        self.balance(rotations);
+    }
+
+    /// Builds a perfectly balanced subtree out of the next `n` values of `vals`, which must be in increasing order
+    pub(crate) fn build_sorted(vals: &mut impl Iterator<Item = T>, n: usize) -> Option<Box<Node<T>>> {
+        if n == 0 {
+            return None;
+        }
+        let left = Self::build_sorted(vals, n / 2);
+        let val = vals.next()?;
+        let right = Self::build_sorted(vals, n - n / 2 - 1);
+        let mut node = Box::new(Node {
+            height: 1,
+            size: 1,
+            val,
+            left,
+            right,
+        });
+        node.update_height();
+        Some(node)
+    }
+
+    /// Like `build_sorted`, but allocates every node before its children so that the nodes land in memory in
+    /// depth-first (pre-order) order. Every slot of `slots` must be `Some` and the values must be in increasing order
+    pub(crate) fn build_preorder(slots: &mut [Option<T>]) -> Option<Box<Node<T>>> {
+        if slots.is_empty() {
+            return None;
+        }
+        let mid = slots.len() / 2;
+        let mut node = Box::new(Node {
+            height: 1,
+            size: 1,
+            val: slots[mid].take()?,
+            left: None,
+            right: None,
+        });
+        let (left, right) = slots.split_at_mut(mid);
+        node.left = Self::build_preorder(left);
+        node.right = Self::build_preorder(&mut right[1..]);
+        node.update_height();
+        Some(node)
+    }
+
+    /// Counts the elements for which `f` reports that the target lies strictly after them
+    pub(crate) fn count_less_by(&self, mut f: impl FnMut(&T) -> Ordering) -> usize {
+        match f(&self.val) {
+            Ordering::Less | Ordering::Equal => self.left.as_ref().map(|l| l.count_less_by(f)).unwrap_or(0),
+            Ordering::Greater => {
+                self.left_size() + 1 + self.right.as_ref().map(|r| r.count_less_by(f)).unwrap_or(0)
+            }
+        }
+    }
+
+    /// Compares the shape and values of two subtrees node by node
+    pub(crate) fn structurally_eq(&self, other: &Node<T>) -> bool
+    where
+        T: PartialEq,
+    {
+        fn children_eq<T: PartialEq>(a: &Option<Box<Node<T>>>, b: &Option<Box<Node<T>>>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a.structurally_eq(b),
+                (None, None) => true,
+                _ => false,
+            }
+        }
+        self.val == other.val && children_eq(&self.left, &other.left) && children_eq(&self.right, &other.right)
+    }
+
+    /// Calls `f` in increasing order on every element that is neither `before` nor `after` the window,
+    /// skipping the subtrees that lie entirely outside of it
+    pub(crate) fn for_each_mut_within(
+        &mut self,
+        before: &impl Fn(&T) -> bool,
+        after: &impl Fn(&T) -> bool,
+        f: &mut impl FnMut(&mut T),
+    ) {
+        let (is_before, is_after) = (before(&self.val), after(&self.val));
+        if !is_before {
+            if let Some(left) = &mut self.left {
+                left.for_each_mut_within(before, after, f);
+            }
+        }
+        if !is_before && !is_after {
+            f(&mut self.val);
+        }
+        if !is_after {
+            if let Some(right) = &mut self.right {
+                right.for_each_mut_within(before, after, f);
+            }
+        }
+    }
+
+    /// Folds over the elements in increasing order, handing `f` mutable access to each one
+    pub(crate) fn fold_mut<B>(&mut self, init: B, f: &mut impl FnMut(B, &mut T) -> B) -> B {
+        let mut acc = init;
+        if let Some(left) = &mut self.left {
+            acc = left.fold_mut(acc, f);
+        }
+        acc = f(acc, &mut self.val);
+        if let Some(right) = &mut self.right {
+            acc = right.fold_mut(acc, f);
+        }
+        acc
+    }
+
+    pub(crate) fn try_for_each<'a, B>(&'a self, f: &mut impl FnMut(&'a T) -> ControlFlow<B>) -> ControlFlow<B> {
+        if let Some(left) = &self.left {
+            left.try_for_each(f)?;
+        }
+        f(&self.val)?;
+        if let Some(right) = &self.right {
+            right.try_for_each(f)?;
+        }
+        ControlFlow::Continue(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,321 @@
+//! This module contains a persistent AVL tree whose versions share structure.
+//!
+//! Nodes are atomically reference counted, so cloning a tree is `O(1)` and every update copies only the nodes on
+//! the path it touches that are shared with another version. Older versions stay valid and unchanged, and can be
+//! read from other threads.
+
+use std::{cmp::Ordering, sync::Arc};
+
+mod snapshot;
+pub use snapshot::*;
+
+type Link<T> = Option<Arc<PNode<T>>>;
+
+#[derive(Clone)]
+struct PNode<T> {
+    height: i32,
+    val: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+fn height<T>(link: &Link<T>) -> i32 {
+    link.as_ref().map(|n| n.height).unwrap_or(0)
+}
+
+impl<T> PNode<T> {
+    fn update_height(&mut self) {
+        self.height = 1 + i32::max(height(&self.left), height(&self.right));
+    }
+
+    fn bf(&self) -> i32 {
+        height(&self.left) - height(&self.right)
+    }
+}
+
+/// Rotations mirror the ones of `Node`, except that every node they modify is first detached from
+/// the other versions sharing it with `Arc::make_mut`
+fn rotate_left<T: Clone>(node: &mut Arc<PNode<T>>) {
+    let head = Arc::make_mut(node);
+    if let Some(mut new_head) = head.right.take() {
+        let new = Arc::make_mut(&mut new_head);
+        head.right = new.left.take();
+        head.update_height();
+        let old_head = std::mem::replace(node, new_head);
+        let head = Arc::make_mut(node);
+        head.left = Some(old_head);
+        head.update_height();
+    }
+}
+
+fn rotate_right<T: Clone>(node: &mut Arc<PNode<T>>) {
+    let head = Arc::make_mut(node);
+    if let Some(mut new_head) = head.left.take() {
+        let new = Arc::make_mut(&mut new_head);
+        head.left = new.right.take();
+        head.update_height();
+        let old_head = std::mem::replace(node, new_head);
+        let head = Arc::make_mut(node);
+        head.right = Some(old_head);
+        head.update_height();
+    }
+}
+
+fn balance<T: Clone>(node: &mut Arc<PNode<T>>) {
+    let bf = node.bf();
+    if bf > 1 {
+        if let Some(left) = &mut Arc::make_mut(node).left {
+            if left.bf() < 0 {
+                rotate_left(left);
+            }
+        }
+        rotate_right(node);
+    } else if bf < -1 {
+        if let Some(right) = &mut Arc::make_mut(node).right {
+            if right.bf() > 0 {
+                rotate_right(right);
+            }
+        }
+        rotate_left(node);
+    }
+}
+
+/// Inserts `val`, replacing an equal element, and returns whether it was new
+fn insert<T: Ord + Clone>(link: &mut Link<T>, val: T) -> bool {
+    let Some(node) = link else {
+        *link = Some(Arc::new(PNode { height: 1, val, left: None, right: None }));
+        return true;
+    };
+    let n = Arc::make_mut(node);
+    let inserted = match val.cmp(&n.val) {
+        Ordering::Less => insert(&mut n.left, val),
+        Ordering::Equal => {
+            n.val = val;
+            false
+        }
+        Ordering::Greater => insert(&mut n.right, val),
+    };
+    n.update_height();
+    balance(node);
+    inserted
+}
+
+/// Detaches the minimum of a non-empty subtree and returns it
+fn pop_min<T: Clone>(link: &mut Link<T>) -> Option<T> {
+    let node = link.as_mut()?;
+    if node.left.is_some() {
+        let n = Arc::make_mut(node);
+        let min = pop_min(&mut n.left);
+        n.update_height();
+        balance(node);
+        min
+    } else {
+        let PNode { val, right, .. } = Arc::unwrap_or_clone(link.take()?);
+        *link = right;
+        Some(val)
+    }
+}
+
+/// Removes an element equal to `val`, which the caller has checked to be present
+fn remove<T: Ord + Clone>(link: &mut Link<T>, val: &T) {
+    let Some(node) = link else {
+        return;
+    };
+    match val.cmp(&node.val) {
+        Ordering::Less => remove(&mut Arc::make_mut(node).left, val),
+        Ordering::Greater => remove(&mut Arc::make_mut(node).right, val),
+        Ordering::Equal => {
+            let n = Arc::make_mut(node);
+            match (n.left.is_some(), n.right.is_some()) {
+                (true, true) => {
+                    if let Some(min) = pop_min(&mut n.right) {
+                        n.val = min;
+                    }
+                }
+                _ => {
+                    let child = n.left.take().or_else(|| n.right.take());
+                    *link = child;
+                    return;
+                }
+            }
+        }
+    }
+    if let Some(node) = link {
+        Arc::make_mut(node).update_height();
+        balance(node);
+    }
+}
+
+/// ## Description
+///
+/// A persistent AVL tree holding a sorted set of distinct elements.
+///
+/// `insert` and `remove` leave `self` untouched and return a new version of the tree which shares every
+/// subtree the update did not touch, so each update allocates only `O(log(n))` nodes and cloning any version
+/// is `O(1)`. Elements are cloned only when a node they live in has to be copied.
+///
+/// `insert_mut` and `remove_mut` update a version in place instead, copying only the nodes still shared with other
+/// versions, so a writer can keep changing one tree while readers on other threads hold `snapshot`s of it.
+pub struct PersistentAVL<T> {
+    root: Link<T>,
+    len: usize,
+}
+
+impl<T> Clone for PersistentAVL<T> {
+    fn clone(&self) -> Self {
+        Self { root: self.root.clone(), len: self.len }
+    }
+}
+
+impl<T> Default for PersistentAVL<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PersistentAVL<T> {
+    /// Creates and returns a new, empty persistent tree
+    #[inline]
+    pub fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        height(&self.root) as usize
+    }
+
+    /// Returns whether `self` and `other` are backed by the very same root node
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns an immutable `Snapshot` of this version in `O(1)`, sharing every node with it.
+    ///
+    /// The snapshot can be sent to other threads and read there while this tree keeps being modified through
+    /// `insert_mut` and `remove_mut`, which copy the nodes they would change as long as the snapshot still holds them.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot { tree: self.clone() }
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some(&node.val)
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some(&node.val)
+    }
+
+    /// Returns the elements in increasing order
+    pub fn increasing(&self) -> impl Iterator<Item = &T> {
+        let mut stack = Vec::new();
+        let mut cur = self.root.as_deref();
+        std::iter::from_fn(move || {
+            while let Some(node) = cur {
+                stack.push(node);
+                cur = node.left.as_deref();
+            }
+            let node = stack.pop()?;
+            cur = node.right.as_deref();
+            Some(&node.val)
+        })
+    }
+}
+
+impl<T: Ord> PersistentAVL<T> {
+    pub fn contains(&self, target: &T) -> bool {
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            cur = match target.cmp(&node.val) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Equal => return true,
+                Ordering::Greater => node.right.as_deref(),
+            }
+        }
+        false
+    }
+}
+
+impl<T: Ord + Clone> PersistentAVL<T> {
+    /// Returns a new version of the tree that also holds `val`, replacing an equal element if there is one
+    pub fn insert(&self, val: T) -> Self {
+        let mut next = self.clone();
+        if insert(&mut next.root, val) {
+            next.len += 1;
+        }
+        next
+    }
+
+    /// Returns a new version of the tree without the element equal to `val`.
+    /// If there is no such element the returned version shares the whole tree with `self`.
+    pub fn remove(&self, val: &T) -> Self {
+        let mut next = self.clone();
+        if self.contains(val) {
+            remove(&mut next.root, val);
+            next.len -= 1;
+        }
+        next
+    }
+
+    /// Inserts `val` into this version in place, replacing an equal element, and returns whether it was new.
+    ///
+    /// Nodes on the path that no other version or snapshot holds are updated in place; only the shared ones are
+    /// copied, so a writer taking periodic snapshots copies `O(log(n))` nodes per update at most.
+    pub fn insert_mut(&mut self, val: T) -> bool {
+        let inserted = insert(&mut self.root, val);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Removes the element equal to `val` from this version in place and returns whether there was one, copying only
+    /// the nodes on the path that are shared with other versions or snapshots.
+    pub fn remove_mut(&mut self, val: &T) -> bool {
+        if !self.contains(val) {
+            return false;
+        }
+        remove(&mut self.root, val);
+        self.len -= 1;
+        true
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for PersistentAVL<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        for val in iter {
+            if insert(&mut tree.root, val) {
+                tree.len += 1;
+            }
+        }
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,99 @@
+use std::{cmp::Ordering, ops::RangeBounds};
+
+use crate::{node::{after_end, before_start}, Nearness};
+
+use super::{PNode, PersistentAVL};
+
+/// An immutable view of one version of a `PersistentAVL`, taken by `PersistentAVL::snapshot`.
+///
+/// A snapshot shares its nodes with the tree it was taken from, so taking and cloning one are both `O(1)` and no
+/// element is copied. While the snapshot is alive, the writer's `insert_mut` and `remove_mut` copy a node before
+/// changing it, leaving the snapshot exactly as it was. It is `Send` and `Sync` when `T` is, so it can be handed to
+/// worker threads that keep reading it while the tree is updated.
+pub struct Snapshot<T> {
+    pub(crate) tree: PersistentAVL<T>,
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Self {
+        Self { tree: self.tree.clone() }
+    }
+}
+
+impl<T> Snapshot<T> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Returns the elements in increasing order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.increasing()
+    }
+
+    /// Returns the version of the tree this snapshot shows, again sharing every node with it
+    #[inline]
+    pub fn to_persistent(&self) -> PersistentAVL<T> {
+        self.tree.clone()
+    }
+}
+
+impl<T: Ord> Snapshot<T> {
+    #[inline]
+    pub fn contains(&self, target: &T) -> bool {
+        self.tree.contains(target)
+    }
+
+    /// Returns the elements lying in `range` in increasing order, skipping the subtrees outside of it
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> impl Iterator<Item = &T> {
+        fn descend<'a, T: Ord>(range: &impl RangeBounds<T>, mut cur: Option<&'a PNode<T>>, stack: &mut Vec<&'a PNode<T>>) {
+            while let Some(node) = cur {
+                if before_start(range, &node.val) {
+                    cur = node.right.as_deref();
+                } else {
+                    stack.push(node);
+                    cur = node.left.as_deref();
+                }
+            }
+        }
+        let mut stack = Vec::new();
+        descend(&range, self.tree.root.as_deref(), &mut stack);
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            if after_end(&range, &node.val) {
+                stack.clear();
+                return None;
+            }
+            descend(&range, node.right.as_deref(), &mut stack);
+            Some(&node.val)
+        })
+    }
+}
+
+impl<T: Ord + Nearness> Snapshot<T> {
+    /// Returns the element nearest to `target`, looking along its search path and, for circular types, also at the
+    /// smallest and the largest element, as `AVL::nearest` does.
+    pub fn nearest<'a>(&'a self, target: &'a T) -> Option<&'a T> {
+        let mut best: Option<&T> = None;
+        let mut cur = self.tree.root.as_deref();
+        while let Some(node) = cur {
+            best = Some(best.map_or(&node.val, |b| T::nearer(&node.val, b, target)));
+            cur = match target.cmp(&node.val) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Equal => return Some(&node.val),
+                Ordering::Greater => node.right.as_deref(),
+            };
+        }
+        let found = best?;
+        if !T::CIRCULAR {
+            return Some(found);
+        }
+        Some([self.tree.min(), self.tree.max()].into_iter().flatten().fold(found, |best, end| T::nearer(end, best, target)))
+    }
+}
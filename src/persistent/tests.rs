@@ -0,0 +1,136 @@
+use super::*;
+
+/// A small deterministic pseudo-random generator so that the randomized tests are reproducible
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+#[test]
+fn old_versions_are_unaffected() {
+    let v0: PersistentAVL<i32> = (0..1000).collect();
+    assert_eq!(v0.len(), 1000);
+    assert_eq!(v0.height(), 10);
+
+    let v1 = v0.insert(5000);
+    assert_eq!(v0.len(), 1000);
+    assert!(!v0.contains(&5000));
+    assert!(v1.contains(&5000));
+    assert_eq!(v1.len(), 1001);
+
+    let mut state = 5;
+    let mut cur = v1.clone();
+    let mut reference: std::collections::BTreeSet<i32> = (0..1000).chain([5000]).collect();
+    for _ in 0..3000 {
+        let x = (lcg(&mut state) % 1200) as i32;
+        if lcg(&mut state).is_multiple_of(2) {
+            cur = cur.remove(&x);
+            reference.remove(&x);
+        } else {
+            cur = cur.insert(x);
+            reference.insert(x);
+        }
+        assert_eq!(cur.len(), reference.len());
+    }
+    assert!(cur.increasing().copied().eq(reference.iter().copied()));
+    assert!(cur.height() <= 14);
+    assert!(v0.increasing().copied().eq(0..1000));
+    assert!(v1.remove(&-1).ptr_eq(&v1));
+}
+
+#[test]
+fn untouched_subtrees_are_shared() {
+    let v0: PersistentAVL<i32> = (0..1000).collect();
+    let v1 = v0.insert(5000);
+    let (Some(a), Some(b)) = (&v0.root, &v1.root) else {
+        panic!("both versions are non-empty")
+    };
+    assert!(!Arc::ptr_eq(a, b));
+    let (Some(left_a), Some(left_b)) = (&a.left, &b.left) else {
+        panic!("the root has a left subtree")
+    };
+    assert!(Arc::ptr_eq(left_a, left_b));
+}
+
+/// Counts the nodes of `a` that are the very same allocation as a node of `b`
+fn shared_nodes<T>(a: &Link<T>, b: &Link<T>) -> usize {
+    fn addrs<T>(link: &Link<T>, out: &mut Vec<usize>) {
+        if let Some(node) = link {
+            out.push(Arc::as_ptr(node) as usize);
+            addrs(&node.left, out);
+            addrs(&node.right, out);
+        }
+    }
+    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+    addrs(a, &mut xs);
+    addrs(b, &mut ys);
+    ys.sort();
+    xs.iter().filter(|x| ys.binary_search(x).is_ok()).count()
+}
+
+#[test]
+fn snapshot_stays_fixed_while_the_tree_changes() {
+    let mut tree: PersistentAVL<i32> = (0..1000).collect();
+    let snap = tree.snapshot();
+    let reader = {
+        let snap = snap.clone();
+        std::thread::spawn(move || (snap.len(), snap.contains(&500), snap.iter().sum::<i32>()))
+    };
+    assert!(tree.insert_mut(5000));
+    assert!(tree.remove_mut(&500));
+    assert!(!tree.remove_mut(&500));
+    assert_eq!(reader.join().unwrap(), (1000, true, 499500));
+    assert!(snap.iter().copied().eq(0..1000));
+    assert!(!snap.contains(&5000));
+    assert_eq!(tree.len(), 1000);
+    assert!(tree.contains(&5000) && !tree.contains(&500));
+
+    // the writer only copied the nodes on the two paths it changed
+    let shared = shared_nodes(&snap.tree.root, &tree.root);
+    assert!(shared >= 1000 - 2 * 12, "only {shared} nodes are shared");
+}
+
+#[test]
+fn unshared_nodes_are_updated_in_place() {
+    let mut tree: PersistentAVL<i32> = (0..100).collect();
+    let before = tree.root.as_ref().map(Arc::as_ptr);
+    tree.insert_mut(100);
+    tree.remove_mut(&0);
+    assert_eq!(tree.root.as_ref().map(Arc::as_ptr), before);
+    assert!(tree.increasing().copied().eq(1..=100));
+}
+
+#[test]
+fn snapshot_queries_match_a_linear_scan() {
+    let mut state = 9;
+    let tree: PersistentAVL<u64> = (0..500).map(|_| lcg(&mut state) % 3000).collect();
+    let snap = tree.snapshot();
+    let vals: Vec<u64> = tree.increasing().copied().collect();
+    for target in (0..3100).step_by(7) {
+        let best = vals.iter().map(|v| v.abs_diff(target)).min();
+        assert_eq!(
+            snap.nearest(&target).map(|v| v.abs_diff(target)),
+            best,
+            "nearest {target}"
+        );
+        let lo = target.saturating_sub(40);
+        assert!(snap
+            .range(lo..target)
+            .eq(vals.iter().filter(|v| (lo..target).contains(*v))));
+        assert!(snap
+            .range(lo..=target)
+            .eq(vals.iter().filter(|v| (lo..=target).contains(*v))));
+    }
+    assert!(snap.range(..).eq(vals.iter()));
+    assert_eq!(PersistentAVL::<u64>::new().snapshot().nearest(&4), None);
+
+    use std::num::Wrapping;
+    let ring: PersistentAVL<Wrapping<u16>> =
+        [65534, 100, 30000].into_iter().map(Wrapping).collect();
+    assert_eq!(
+        ring.snapshot().nearest(&Wrapping(2)),
+        Some(&Wrapping(65534))
+    );
+}
@@ -0,0 +1,75 @@
+use super::*;
+
+#[test]
+fn signed_nearness_at_the_extremes() {
+    assert_eq!(i8::MIN.nearer(&i8::MAX, &1), &i8::MAX);
+    assert_eq!(i8::MIN.nearer(&i8::MAX, &-1), &i8::MIN);
+    assert_eq!(i8::MIN.farther(&i8::MAX, &1), &i8::MIN);
+
+    let tree: AVL<i8> = [i8::MIN, -100, 0, 90, i8::MAX].into_iter().collect();
+    assert_eq!(tree.nearest(&100), Some(&90));
+    assert_eq!(tree.nearest(&120), Some(&i8::MAX));
+    assert_eq!(tree.nearest(&-120), Some(&i8::MIN));
+    assert_eq!(tree.farthest(&i8::MAX), Some(&i8::MIN));
+    assert_eq!(tree.farthest(&i8::MIN), Some(&i8::MAX));
+}
+
+#[test]
+fn unsigned_nearness_never_underflows() {
+    // target below both
+    assert_eq!(10u8.nearer(&20, &u8::MIN), &10);
+    assert_eq!(10u8.farther(&20, &u8::MIN), &20);
+    // target above both
+    assert_eq!(200u8.nearer(&250, &u8::MAX), &250);
+    assert_eq!(200u8.farther(&250, &u8::MAX), &200);
+    // target between
+    assert_eq!(u8::MIN.nearer(&u8::MAX, &200), &u8::MAX);
+    assert_eq!(u8::MIN.farther(&u8::MAX, &200), &u8::MIN);
+    assert_eq!(5u8.nearer(&3, &5), &5);
+    assert_eq!(3u8.nearer(&5, &5), &5);
+
+    let tree: AVL<u8> = [0, 1, 128, 254, 255].into_iter().collect();
+    for q in 0..=u8::MAX {
+        let found = *tree.nearest(&q).unwrap();
+        assert!(
+            tree.increasing()
+                .all(|&x| x.abs_diff(q) >= found.abs_diff(q)),
+            "{q}"
+        );
+    }
+}
+
+#[test]
+fn wrapping_nearness_is_circular() {
+    assert_eq!(
+        Wrapping(65534u16).nearer(&Wrapping(100), &Wrapping(2)),
+        &Wrapping(65534)
+    );
+    assert_eq!(
+        Wrapping(65534u16).farther(&Wrapping(100), &Wrapping(2)),
+        &Wrapping(100)
+    );
+    assert_eq!(
+        Wrapping(10u8).nearer(&Wrapping(250), &Wrapping(0)),
+        &Wrapping(250)
+    );
+    assert_eq!(
+        Wrapping(u16::MAX).nearer(&Wrapping(3), &Wrapping(0)),
+        &Wrapping(u16::MAX)
+    );
+}
+
+#[test]
+fn wrapping_nearest_looks_across_the_wraparound() {
+    let tree: AVL<Wrapping<u16>> = [65534, 100, 30000].into_iter().map(Wrapping).collect();
+    assert_eq!(tree.nearest(&Wrapping(2)), Some(&Wrapping(65534)));
+    assert_eq!(tree.nearest(&Wrapping(65530)), Some(&Wrapping(65534)));
+    assert_eq!(tree.nearest(&Wrapping(120)), Some(&Wrapping(100)));
+
+    let tree: AVL<Wrapping<u16>> = [5, 40000, 65000].into_iter().map(Wrapping).collect();
+    assert_eq!(tree.nearest(&Wrapping(u16::MAX)), Some(&Wrapping(5)));
+    assert_eq!(
+        tree.nearest_kind(&Wrapping(u16::MAX)),
+        Some((NearestKind::Approximate, &Wrapping(5)))
+    );
+}